@@ -0,0 +1,133 @@
+// Free functions that create sequences, rather than adapting an existing one. These are
+// the equivalent of C#'s static `Enumerable` methods such as `Range` and `Repeat`.
+
+use std::convert::TryFrom;
+use std::iter;
+
+/// The equivalent of C#'s `Enumerable.Range`: yields `count` consecutive integers
+/// starting at `start`.
+///
+/// # Panics
+/// Like C#, panics up front if the last value, `start + count - 1`, would be greater
+/// than `i64::MAX`.
+pub fn range(start: i64, count: usize) -> impl Iterator<Item = i64> {
+    if count > 0 {
+        let last = i64::try_from(count - 1).ok().and_then(|n| start.checked_add(n));
+        assert!(last.is_some(), "range would go past i64::MAX");
+    }
+    (0..count).map(move |i| start + i as i64)
+}
+
+/// The equivalent of C#'s `Enumerable.Repeat`: yields `value` `count` times.
+#[inline]
+pub fn repeat_count<T: Clone>(value: T, count: usize) -> impl Iterator<Item = T> {
+    iter::repeat_n(value, count)
+}
+
+/// Yields `seed`, then the result of repeatedly applying `step` to the previous value, for
+/// as long as `pred` holds; the iterator equivalent of a `for` loop. `pred` is checked
+/// against the seed too, so if it is false for the seed nothing is yielded. As in a `for`
+/// loop, `step` is only applied to values that satisfied `pred`.
+#[inline]
+pub fn iterate_while<T, FStep, FPred>(seed: T, pred: FPred, step: FStep) -> impl Iterator<Item = T>
+    where FStep: Fn(&T) -> T,
+          FPred: Fn(&T) -> bool
+{
+    iter::successors(Some(seed).filter(&pred), move |x| Some(step(x)).filter(&pred))
+}
+
+/// Lazily chains any number of sequences end to end; the many-way version of `chain`.
+#[inline]
+pub fn concat_all<I, T>(iters: I) -> impl Iterator<Item = T>
+    where I: IntoIterator,
+          I::Item: IntoIterator<Item = T>
+{
+    iters.into_iter().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{concat_all, iterate_while, range, repeat_count};
+    use std::cell::Cell;
+
+    #[test]
+    fn range_yields_count_consecutive_integers() {
+        assert_eq!(range(5, 3).collect::<Vec<_>>(), vec![5, 6, 7]);
+        assert_eq!(range(-1, 3).collect::<Vec<_>>(), vec![-1, 0, 1]);
+    }
+
+    #[test]
+    fn range_can_end_at_i64_max() {
+        assert_eq!(range(i64::MAX - 1, 2).collect::<Vec<_>>(), vec![i64::MAX - 1, i64::MAX]);
+        assert_eq!(range(i64::MAX, 0).next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "range would go past i64::MAX")]
+    fn range_past_i64_max_panics() {
+        let _ = range(i64::MAX, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "range would go past i64::MAX")]
+    fn range_with_count_too_large_for_i64_panics() {
+        let _ = range(0, usize::MAX);
+    }
+
+    #[test]
+    fn range_with_zero_count_is_empty() {
+        assert_eq!(range(0, 0).next(), None);
+    }
+
+    #[test]
+    fn repeat_count_yields_value_count_times() {
+        assert_eq!(repeat_count("x", 2).collect::<Vec<_>>(), vec!["x", "x"]);
+        assert_eq!(repeat_count("x", 0).next(), None);
+    }
+
+    #[test]
+    fn iterate_while_generates_powers_of_two() {
+        let powers = iterate_while(1, |&x| x < 100, |&x| x * 2).collect::<Vec<_>>();
+        assert_eq!(powers, vec![1, 2, 4, 8, 16, 32, 64]);
+    }
+
+    #[test]
+    fn iterate_while_does_not_step_past_last_value() {
+        let values = iterate_while(250u8, |&x| x < 255, |&x| x + 1).collect::<Vec<_>>();
+        assert_eq!(values, vec![250, 251, 252, 253, 254]);
+
+        // One step per value that satisfied the predicate, none for the value that failed it.
+        let steps = Cell::new(0);
+        let values = iterate_while(1, |&x| x < 4, |&x| { steps.set(steps.get() + 1); x + 1 }).collect::<Vec<_>>();
+        assert_eq!(values, vec![1, 2, 3]);
+        assert_eq!(steps.get(), 3);
+    }
+
+    #[test]
+    fn iterate_while_with_false_predicate_yields_nothing() {
+        assert_eq!(iterate_while(1, |&x| x > 100, |&x| x + 1).next(), None);
+    }
+
+    #[test]
+    fn iterate_while_can_be_bounded_by_count() {
+        let odds = iterate_while(1, |_| true, |&x| x + 2).take(4).collect::<Vec<_>>();
+        assert_eq!(odds, vec![1, 3, 5, 7]);
+    }
+
+    #[test]
+    fn concat_all_chains_every_sequence() {
+        let all = concat_all(vec![vec![1, 2], vec![3], vec![4, 5]]).collect::<Vec<_>>();
+        assert_eq!(all, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn concat_all_skips_empty_sequences() {
+        let all = concat_all(vec![vec![], vec![1], vec![], vec![2, 3], vec![]]).collect::<Vec<_>>();
+        assert_eq!(all, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn concat_all_of_no_sequences_is_empty() {
+        assert_eq!(concat_all(Vec::<Vec<i32>>::new()).next(), None);
+    }
+}
@@ -3,8 +3,23 @@
 /// Type 2 - an adapter that returns a single value of the same type as the iterator
 /// Type 3 - an adapter that returns another iterator.
 
+use rand::Rng;
+use std::cell::RefCell;
+use std::cmp::{self, Ordering};
+use std::error;
+use std::fmt;
 use std::hash;
-use std::collections::HashSet;
+#[cfg(feature = "serde")]
+use std::io;
+use std::iter::{self, Peekable};
+use std::mem;
+use std::ops::{Add, Bound, RangeBounds, Sub};
+use std::rc::Rc;
+use std::slice;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::convert::TryFrom;
+use std::vec;
 
 // Step 1: Define a trait. The trait should extend Iterator so that if we
 // pass a `LinqIteratorExtensions` trait object to a function it will have
@@ -84,208 +99,7924 @@ pub trait LinqIteratorExtensions : Iterator {
               Self::Item: hash::Hash + Eq,
               U: IntoIterator<Item = Self::Item>
     {
-        Intersect { a: self, b: other.into_iter(), items: HashSet::new() }
+        Intersect { a: self, b: Some(other.into_iter()), items: HashSet::new() }
     }
+
+    #[inline]
+    fn single_where<P>(&mut self, mut predicate: P) -> Option<Self::Item>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.try_single_where(&mut predicate).ok()
+    }
+
+    /// Like `single_where`, but tells you why there was no single match.
+    #[inline]
+    fn try_single_where<P>(&mut self, mut predicate: P) -> Result<Self::Item, SingleError>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        match self.find(|x| predicate(x)) {
+            None => Err(SingleError::NoMatch),
+            Some(x) => match self.find(|x| predicate(x)) {
+                None => Ok(x),
+                Some(_) => Err(SingleError::MultipleMatches)
+            }
+        }
+    }
+
+    #[inline]
+    fn last_where<P>(self, mut predicate: P) -> Option<Self::Item>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.filter(|x| predicate(x)).last()
+    }
+
+    #[inline]
+    fn last_where_or<P>(self, predicate: P, default: Self::Item) -> Self::Item
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.last_where(predicate).unwrap_or(default)
+    }
+
+    #[inline]
+    fn last_where_or_default<P>(self, predicate: P) -> Self::Item
+        where Self: Sized,
+              Self::Item: Default,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.last_where_or(predicate, Default::default())
+    }
+
+    /// The equivalent of C#'s `!sequence.Any()`. Note that this consumes the first element
+    /// of a non-empty sequence; wrap the iterator in a `Peekable` and use `peek` instead if
+    /// you need to carry on iterating afterwards.
+    #[inline]
+    fn is_empty(&mut self) -> bool {
+        self.next().is_none()
+    }
+
+    /// Splits the sequence into exactly `parts` vectors whose lengths differ by at most one,
+    /// with the earlier parts getting the extra elements. Panics if `parts` is zero.
+    fn split_into(self, parts: usize) -> Vec<Vec<Self::Item>>
+        where Self: Sized
+    {
+        assert!(parts > 0, "parts must be greater than zero");
+
+        let mut items = self.collect::<Vec<_>>().into_iter();
+        let len = items.len();
+        (0..parts).map(|i| {
+            let size = len / parts + if i < len % parts { 1 } else { 0 };
+            items.by_ref().take(size).collect()
+        }).collect()
+    }
+
+    /// Sorts by each comparator in turn, the first one that does not return `Equal` deciding
+    /// the order. This lets you build up a multi-key sort at runtime. The sort is stable.
+    fn order_by_many(self, comparators: Vec<BoxedComparer<Self::Item>>) -> OrderByMany<Self::Item>
+        where Self: Sized
+    {
+        let mut items = self.collect::<Vec<_>>();
+        items.sort_by(|a, b| {
+            comparators.iter()
+                .map(|cmp| cmp(a, b))
+                .find(|&ord| ord != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        OrderByMany { items: items.into_iter() }
+    }
+
+    /// Puts each element into the bucket `floor(key / bucket_width)` and returns the
+    /// non-empty buckets in ascending order. Panics if `bucket_width` is not positive.
+    fn group_by_bucket<F>(self, bucket_width: f64, mut key: F) -> Vec<(i64, Vec<Self::Item>)>
+        where Self: Sized,
+              F: FnMut(&Self::Item) -> f64
+    {
+        assert!(bucket_width > 0.0, "bucket_width must be positive");
+
+        let mut buckets = BTreeMap::new();
+        for x in self {
+            let bucket = (key(&x) / bucket_width).floor() as i64;
+            buckets.entry(bucket).or_insert_with(Vec::new).push(x);
+        }
+        buckets.into_iter().collect()
+    }
+
+    /// Eagerly skips the leading run of elements that match the predicate, returning how
+    /// many were skipped along with an iterator over the rest of the sequence.
+    fn skip_while_counting<P>(mut self, mut predicate: P) -> (usize, SkipWhileCounting<Self>)
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        let mut skipped = 0;
+        let first = loop {
+            match self.next() {
+                Some(ref x) if predicate(x) => skipped += 1,
+                other => break other
+            }
+        };
+
+        (skipped, SkipWhileCounting { first, iter: self })
+    }
+
+    /// Folds each run of adjacent elements that share a key into a single element, yielding
+    /// `(key, reduced)` pairs. Only adjacent elements are combined, so the input should
+    /// normally be sorted by the key already.
+    #[inline]
+    fn reduce_adjacent_by<K, KF, RF>(self, key_selector: KF, reduce: RF) -> ReduceAdjacentBy<Self, K, KF, RF>
+        where Self: Sized,
+              K: PartialEq,
+              KF: FnMut(&Self::Item) -> K,
+              RF: FnMut(Self::Item, Self::Item) -> Self::Item
+    {
+        ReduceAdjacentBy { iter: self, key_selector, reduce, pending: None }
+    }
+
+    /// Chunks the sequence from the end towards the start. The chunks come back last-first,
+    /// each in its original internal order, and any short chunk is the last one returned.
+    /// Panics if `size` is zero.
+    fn chunks_rev(self, size: usize) -> Vec<Vec<Self::Item>>
+        where Self: Sized
+    {
+        assert!(size > 0, "size must be greater than zero");
+
+        let mut items = self.collect::<Vec<_>>();
+        let mut chunks = Vec::with_capacity(items.len().div_ceil(size));
+        while !items.is_empty() {
+            let at = items.len().saturating_sub(size);
+            chunks.push(items.split_off(at));
+        }
+        chunks
+    }
+
+    /// Returns the distinct elements in order of first appearance, together with the
+    /// (zero-based) positions of the duplicates that were dropped.
+    fn distinct_reporting(self) -> (Vec<Self::Item>, Vec<usize>)
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone
+    {
+        let mut seen = HashSet::new();
+        let mut distinct = Vec::new();
+        let mut dropped = Vec::new();
+
+        for (idx, x) in self.enumerate() {
+            if seen.insert(x.clone()) {
+                distinct.push(x);
+            } else {
+                dropped.push(idx);
+            }
+        }
+
+        (distinct, dropped)
+    }
+
+    /// Applies `f` to each sliding window of `size` elements. This is the same as the
+    /// (currently unstable) `Iterator::map_windows` in std, and reuses a single buffer rather
+    /// than allocating a new `Vec` for every window. Panics if `size` is zero.
+    #[inline]
+    fn map_windows<R, F>(self, size: usize, f: F) -> MapWindows<Self, F>
+        where Self: Sized,
+              F: FnMut(&[Self::Item]) -> R
+    {
+        assert!(size > 0, "size must be greater than zero");
+        MapWindows { iter: self, size, f, buffer: VecDeque::with_capacity(size) }
+    }
+
+    /// Yields the sequence unchanged except that the element at `index` is replaced by
+    /// `value`. If the sequence is shorter than that, nothing is replaced.
+    #[inline]
+    fn replace_at(self, index: usize, value: Self::Item) -> ReplaceAt<Self>
+        where Self: Sized
+    {
+        ReplaceAt { iter: self, index, pos: 0, value: Some(value) }
+    }
+
+    /// Yields the sequence with `value` inserted so that it appears at position `index`.
+    /// If `index` is at or beyond the end of the sequence, `value` is appended.
+    #[inline]
+    fn insert_at(self, index: usize, value: Self::Item) -> InsertAt<Self>
+        where Self: Sized
+    {
+        InsertAt { iter: self, index, pos: 0, value: Some(value) }
+    }
+
+    /// Yields the sequence with the element at `index` left out. If the sequence is shorter
+    /// than that, nothing is removed.
+    #[inline]
+    fn remove_at(self, index: usize) -> RemoveAt<Self>
+        where Self: Sized
+    {
+        RemoveAt { iter: self, index, pos: 0 }
+    }
+
+    /// The inverse of a group by: flattens each `(key, items)` pair into a `(key, item)`
+    /// pair for every item in the group.
+    #[inline]
+    fn ungroup<K, V>(self) -> Ungroup<Self, K, V>
+        where Self: Sized + Iterator<Item = (K, V)>,
+              K: Clone,
+              V: IntoIterator
+    {
+        Ungroup { iter: self, current: None }
+    }
+
+    /// Counts how many elements fall into each consecutive block of `bucket_size` positions,
+    /// so the i-th count covers positions `i * bucket_size .. (i + 1) * bucket_size`. Every
+    /// count but the last is therefore `bucket_size`, the last holding the remainder.
+    /// Panics if `bucket_size` is zero.
+    fn count_by_index_range(self, bucket_size: usize) -> Vec<usize>
+        where Self: Sized
+    {
+        assert!(bucket_size > 0, "bucket_size must be greater than zero");
+
+        let mut counts = Vec::new();
+        for (idx, _) in self.enumerate() {
+            if idx % bucket_size == 0 {
+                counts.push(0);
+            }
+            if let Some(last) = counts.last_mut() {
+                *last += 1;
+            }
+        }
+        counts
+    }
+
+    /// The equivalent of C#'s `SelectMany(collectionSelector, resultSelector)`: each element
+    /// is projected to a collection, and every item in that collection is then combined with
+    /// the element it came from using `result`.
+    #[inline]
+    fn select_many_with<U, R, CF, RF>(self, collection: CF, result: RF) -> SelectManyWith<Self, U, CF, RF>
+        where Self: Sized,
+              Self::Item: Clone,
+              U: IntoIterator,
+              CF: FnMut(&Self::Item) -> U,
+              RF: FnMut(&Self::Item, U::Item) -> R
+    {
+        SelectManyWith { iter: self, collection, result, current: None }
+    }
+
+    /// Writes each element to `writer` as newline-delimited JSON, one object per line.
+    #[cfg(feature = "serde")]
+    fn write_json_lines<W: io::Write>(self, writer: &mut W) -> io::Result<()>
+        where Self: Sized,
+              Self::Item: serde::Serialize
+    {
+        for x in self {
+            serde_json::to_writer(&mut *writer, &x)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Groups the elements by key and keeps the `n` largest elements of each group, largest
+    /// first. Groups are returned in the order their keys first appeared.
+    fn top_n_per_group<K, KF>(self, n: usize, key_selector: KF) -> Vec<(K, Vec<Self::Item>)>
+        where Self: Sized,
+              Self::Item: Ord,
+              K: hash::Hash + Eq,
+              KF: FnMut(&Self::Item) -> K
+    {
+        let mut groups = group_in_order(self, key_selector);
+        for &mut (_, ref mut members) in &mut groups {
+            members.sort_by(|a, b| b.cmp(a));
+            members.truncate(n);
+        }
+        groups
+    }
+
+    /// Like `top_n_per_group`, but ranks the elements within each group by the
+    /// result of `rank` rather than by the elements themselves.
+    fn top_n_per_group_by<K, R, KF, RF>(self, n: usize, key_selector: KF, mut rank: RF)
+        -> Vec<(K, Vec<Self::Item>)>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              R: Ord,
+              KF: FnMut(&Self::Item) -> K,
+              RF: FnMut(&Self::Item) -> R
+    {
+        let mut groups = group_in_order(self, key_selector);
+        for &mut (_, ref mut members) in &mut groups {
+            members.sort_by_cached_key(|x| cmp::Reverse(rank(x)));
+            members.truncate(n);
+        }
+        groups
+    }
+
+    /// Draws `self_weight` elements from this sequence, then `other_weight` from `other`,
+    /// and so on. When one sequence runs out the rest of the other is yielded.
+    /// Panics if either weight is zero.
+    #[inline]
+    fn interleave_weighted<U>(self, other: U, self_weight: usize, other_weight: usize)
+        -> InterleaveWeighted<Self, U::IntoIter>
+        where Self: Sized,
+              U: IntoIterator<Item = Self::Item>
+    {
+        assert!(self_weight > 0 && other_weight > 0, "weights must be greater than zero");
+        InterleaveWeighted {
+            a: self,
+            b: other.into_iter(),
+            a_weight: self_weight,
+            b_weight: other_weight,
+            from_a: true,
+            drawn: 0
+        }
+    }
+
+    /// Folds each segment of the sequence separately, yielding one folded value per segment.
+    /// A new segment starts at every element for which `is_boundary` returns true (that
+    /// element is the first one in the new segment).
+    #[inline]
+    fn aggregate_segments<B, P, F>(self, is_boundary: P, init: B, f: F) -> AggregateSegments<Self, B, P, F>
+        where Self: Sized,
+              B: Clone,
+              P: FnMut(&Self::Item) -> bool,
+              F: Fn(B, Self::Item) -> B
+    {
+        AggregateSegments { iter: self, is_boundary, init, f, pending: None }
+    }
+
+    /// Wraps the iterator in a `Peekable`, giving access to `peek_while`.
+    #[inline]
+    fn peekable_linq(self) -> PeekableLinq<Self>
+        where Self: Sized
+    {
+        PeekableLinq { iter: self.peekable() }
+    }
+
+    /// Yields each element that satisfies the predicate, paired with the number of elements
+    /// that came between it and the previous match (or the start of the sequence, for the
+    /// first match). Adjacent matches therefore have a gap of zero.
+    #[inline]
+    fn gaps_between<P>(self, predicate: P) -> GapsBetween<Self, P>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        GapsBetween { iter: self, predicate }
+    }
+
+    /// Collects exactly `N` elements into an array. If the sequence is too short the `Err`
+    /// holds all the elements; if it is too long it holds the first `N + 1` elements (we stop
+    /// reading as soon as we know there are too many).
+    fn to_fixed_array<const N: usize>(self) -> Result<[Self::Item; N], Vec<Self::Item>>
+        where Self: Sized
+    {
+        let items = self.take(N + 1).collect::<Vec<_>>();
+        <[Self::Item; N]>::try_from(items)
+    }
+
+    /// Computes `sum(value * weight) / sum(weight)` in a single pass. Returns `None` if
+    /// the sequence is empty or the weights sum to zero.
+    fn weighted_average<F, G>(self, mut value: F, mut weight: G) -> Option<f64>
+        where Self: Sized,
+              F: FnMut(&Self::Item) -> f64,
+              G: FnMut(&Self::Item) -> f64
+    {
+        let (total, total_weight) = self.fold((0.0, 0.0), |(total, total_weight), x| {
+            let w = weight(&x);
+            (total + value(&x) * w, total_weight + w)
+        });
+
+        if total_weight == 0.0 { None } else { Some(total / total_weight) }
+    }
+
+    /// An approximate `distinct` for very long sequences. An element is only suppressed if
+    /// it is one of the `capacity` most recently seen distinct elements, so memory use is
+    /// bounded by `capacity` rather than by the number of distinct elements.
+    #[inline]
+    fn distinct_recent(self, capacity: usize) -> DistinctRecent<Self>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone
+    {
+        DistinctRecent {
+            iter: self,
+            capacity,
+            generation: 0,
+            last_seen: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity)
+        }
+    }
+
+    /// Sorts by key using the bottom-up merge sort in this module rather than `sort_by_key`.
+    /// The result is the same, but the stability guarantee is right there in the code: when
+    /// two keys are equal the merge always takes the element from the left (earlier) run.
+    fn order_by_merge<K, F>(self, mut key_selector: F) -> OrderByMerge<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: FnMut(&Self::Item) -> K
+    {
+        let keyed = self.map(|x| (key_selector(&x), x)).collect();
+        let sorted = merge_sort(keyed).into_iter().map(|(_, x)| x).collect::<Vec<_>>();
+        OrderByMerge { items: sorted.into_iter() }
+    }
+
+    /// Splits the sequence into the elements classified as `Less`, `Equal` and `Greater`,
+    /// in a single pass and preserving the order within each bucket.
+    fn partition_three<F>(self, mut classify: F) -> ThreeWayPartition<Self::Item>
+        where Self: Sized,
+              F: FnMut(&Self::Item) -> Ordering
+    {
+        let mut less = Vec::new();
+        let mut equal = Vec::new();
+        let mut greater = Vec::new();
+
+        for x in self {
+            match classify(&x) {
+                Ordering::Less => less.push(x),
+                Ordering::Equal => equal.push(x),
+                Ordering::Greater => greater.push(x)
+            }
+        }
+
+        (less, equal, greater)
+    }
+
+    /// Builds a `String` from a sequence of chars, reserving capacity up front from the
+    /// lower bound of the size hint.
+    fn collect_string(self) -> String
+        where Self: Sized + Iterator<Item = char>
+    {
+        let mut s = String::with_capacity(self.size_hint().0);
+        s.extend(self);
+        s
+    }
+
+    /// Concatenates a sequence of string slices. This is plain concatenation; the size
+    /// hint counts slices, not bytes, so no capacity is reserved up front.
+    fn collect_string_from_str<'a>(self) -> String
+        where Self: Sized + Iterator<Item = &'a str>
+    {
+        self.collect()
+    }
+
+    /// Yields one result per element by applying `f` to the window centred on that element.
+    /// The window covers `(size - 1) / 2` elements before the element and `size / 2` after
+    /// it; near the ends of the sequence the window is shortened rather than dropped, so the
+    /// output is always the same length as the input. Panics if `size` is zero.
+    #[inline]
+    fn window_padded<R, F>(self, size: usize, f: F) -> WindowPadded<Self, F>
+        where Self: Sized,
+              F: FnMut(&[Self::Item]) -> R
+    {
+        assert!(size > 0, "size must be greater than zero");
+        WindowPadded {
+            iter: self,
+            before: (size - 1) / 2,
+            after: size / 2,
+            f,
+            buffer: VecDeque::with_capacity(size),
+            current: 0,
+            exhausted: false
+        }
+    }
+
+    /// Like `map`, but threads a mutable state through every call of `f`. Unlike std's
+    /// `scan` there is no way to stop early, so there is always one output per input.
+    #[inline]
+    fn map_stateful<S, R, F>(self, initial: S, f: F) -> MapStateful<Self, S, F>
+        where Self: Sized,
+              F: FnMut(&mut S, Self::Item) -> R
+    {
+        MapStateful { iter: self, state: initial, f }
+    }
+
+    /// The equivalent of C#'s `Except`: yields the distinct elements of this sequence that
+    /// do not appear in `other`, in order of first appearance.
+    #[inline]
+    fn except<U>(self, other: U) -> Except<Self, U::IntoIter>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone,
+              U: IntoIterator<Item = Self::Item>
+    {
+        Except { a: self, b: Some(other.into_iter()), items: HashSet::new() }
+    }
+
+    /// The equivalent of C#'s `Union`: yields the distinct elements of this sequence, then
+    /// those of `other` that have not already been yielded. Both sequences are read lazily.
+    #[inline]
+    fn union<U>(self, other: U) -> Union<Self, U::IntoIter>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone,
+              U: IntoIterator<Item = Self::Item>
+    {
+        Union { a: self, b: other.into_iter(), items: HashSet::new() }
+    }
+
+    /// The equivalent of C#'s `Distinct`: yields each element the first time it is seen,
+    /// skipping any repeats, so the order of first appearance is preserved.
+    #[inline]
+    fn distinct(self) -> Distinct<Self>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone
+    {
+        Distinct { iter: self, items: HashSet::new() }
+    }
+
+    /// Like `distinct`, but compares elements by the key returned by `key`, keeping the
+    /// first element seen for each key. Only the key needs to be hashable.
+    #[inline]
+    fn distinct_by<K, F>(self, key: F) -> DistinctBy<Self, F, K>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              F: FnMut(&Self::Item) -> K
+    {
+        DistinctBy { iter: self, key, keys: HashSet::new() }
+    }
+
+    /// The equivalent of C#'s `GroupBy`. The groupings are returned in the order their keys
+    /// first appeared, and the elements within each grouping keep their original order.
+    fn group_by<K, F>(self, key: F) -> Vec<Grouping<K, Self::Item>>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              F: FnMut(&Self::Item) -> K
+    {
+        group_in_order(self, key).into_iter()
+            .map(|(key, elements)| Grouping { key, elements })
+            .collect()
+    }
+
+    /// The equivalent of C#'s `ToLookup`: a multimap from key to the elements with that
+    /// key, intended for random access rather than iteration.
+    fn to_lookup<K, F>(self, mut key: F) -> Lookup<K, Self::Item>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              F: FnMut(&Self::Item) -> K
+    {
+        let mut groups = HashMap::new();
+        for x in self {
+            groups.entry(key(&x)).or_insert_with(Vec::new).push(x);
+        }
+        Lookup { groups }
+    }
+
+    /// The equivalent of C#'s `Join`, an inner equijoin. The inner sequence is read into a
+    /// lookup up front; then for each outer element, `result` is called with each matching
+    /// inner element in turn. Outer elements with no matches produce nothing.
+    #[inline]
+    fn join<U, K, FO, FI, FR, TInner, TResult>(self, inner: U, outer_key: FO, inner_key: FI, result: FR)
+        -> Join<Self, K, TInner, FO, FR>
+        where Self: Sized,
+              U: IntoIterator<Item = TInner>,
+              K: hash::Hash + Eq,
+              FO: FnMut(&Self::Item) -> K,
+              FI: FnMut(&TInner) -> K,
+              FR: FnMut(&Self::Item, &TInner) -> TResult
+    {
+        Join {
+            outer: self,
+            lookup: inner.into_iter().to_lookup(inner_key),
+            outer_key,
+            result,
+            current: None,
+            index: 0
+        }
+    }
+
+    /// The equivalent of C#'s `GroupJoin`. For each outer element, `result` is called once
+    /// with the element and the (possibly empty) slice of inner elements that match it.
+    #[inline]
+    fn group_join<U, K, FO, FI, FR, TInner, TResult>(self, inner: U, outer_key: FO, inner_key: FI, result: FR)
+        -> GroupJoin<Self, K, TInner, FO, FR>
+        where Self: Sized,
+              U: IntoIterator<Item = TInner>,
+              K: hash::Hash + Eq,
+              FO: FnMut(&Self::Item) -> K,
+              FI: FnMut(&TInner) -> K,
+              FR: FnMut(Self::Item, &[TInner]) -> TResult
+    {
+        GroupJoin {
+            outer: self,
+            lookup: inner.into_iter().to_lookup(inner_key),
+            outer_key,
+            result
+        }
+    }
+
+    /// The equivalent of C#'s `OrderBy`. Further sort keys can be added with `then_by` and
+    /// `then_by_descending`. The elements are buffered and sorted on the first call to `next`;
+    /// the sort is stable.
+    #[inline]
+    fn order_by<K, F>(self, key: F) -> OrderedEnumerable<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: Fn(&Self::Item) -> K + 'static
+    {
+        OrderedEnumerable::new(self.collect(), Box::new(move |a, b| key(a).cmp(&key(b))))
+    }
+
+    /// The equivalent of C#'s `OrderByDescending`.
+    #[inline]
+    fn order_by_descending<K, F>(self, key: F) -> OrderedEnumerable<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: Fn(&Self::Item) -> K + 'static
+    {
+        OrderedEnumerable::new(self.collect(), Box::new(move |a, b| key(b).cmp(&key(a))))
+    }
+
+    /// The equivalent of C#'s seedless `Aggregate`: the first element is the seed. Unlike C#,
+    /// an empty sequence gives `None` rather than an exception.
+    #[inline]
+    fn aggregate<F>(mut self, f: F) -> Option<Self::Item>
+        where Self: Sized,
+              F: FnMut(Self::Item, Self::Item) -> Self::Item
+    {
+        let first = self.next()?;
+        Some(self.fold(first, f))
+    }
+
+    /// The equivalent of C#'s `Aggregate(seed, func)`, better known as `fold`.
+    #[inline]
+    fn aggregate_with<A, F>(self, seed: A, f: F) -> A
+        where Self: Sized,
+              F: FnMut(A, Self::Item) -> A
+    {
+        self.fold(seed, f)
+    }
+
+    /// The equivalent of C#'s `Aggregate(seed, func, resultSelector)`: folds the sequence,
+    /// then transforms the final accumulator with `result`.
+    #[inline]
+    fn aggregate_select<A, F, R, G>(self, seed: A, f: F, result: G) -> R
+        where Self: Sized,
+              F: FnMut(A, Self::Item) -> A,
+              G: FnOnce(A) -> R
+    {
+        result(self.fold(seed, f))
+    }
+
+    /// The equivalent of C#'s `Sum`. This is just std's `sum` with the result type fixed to
+    /// the element type; it can't be called `sum` because that would clash with
+    /// `Iterator::sum` wherever this trait is in scope. As with std, overflow panics in debug
+    /// builds and wraps in release builds.
+    #[inline]
+    fn sum_linq(self) -> Self::Item
+        where Self: Sized,
+              Self::Item: iter::Sum
+    {
+        self.sum()
+    }
+
+    /// The equivalent of C#'s `Average`, except that an empty sequence gives `None` rather
+    /// than an exception. The total is accumulated as an `f64`, so it cannot overflow. Works
+    /// for every primitive number type, including the 64-bit integers (see `ToF64`).
+    fn average(self) -> Option<f64>
+        where Self: Sized,
+              Self::Item: ToF64
+    {
+        let (total, count) = self.fold((0.0, 0usize), |(total, count), x| (total + x.to_f64(), count + 1));
+        if count == 0 { None } else { Some(total / count as f64) }
+    }
+
+    /// The equivalent of C#'s `MinBy`: the element with the smallest key. If several elements
+    /// share the smallest key the first one is returned.
+    fn min_by_key_linq<K, F>(mut self, mut key: F) -> Option<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: FnMut(&Self::Item) -> K
+    {
+        let first = self.next()?;
+        let first_key = key(&first);
+        let (_, min) = self.fold((first_key, first), |(min_key, min), x| {
+            let k = key(&x);
+            if k < min_key { (k, x) } else { (min_key, min) }
+        });
+        Some(min)
+    }
+
+    /// The equivalent of C#'s `MaxBy`: the element with the largest key. If several elements
+    /// share the largest key the first one is returned (std's `max_by_key` returns the last).
+    fn max_by_key_linq<K, F>(mut self, mut key: F) -> Option<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: FnMut(&Self::Item) -> K
+    {
+        let first = self.next()?;
+        let first_key = key(&first);
+        let (_, max) = self.fold((first_key, first), |(max_key, max), x| {
+            let k = key(&x);
+            if k > max_key { (k, x) } else { (max_key, max) }
+        });
+        Some(max)
+    }
+
+    /// The equivalent of C#'s `ToDictionary`. As in C#, a duplicate key is an error rather
+    /// than silently overwriting the earlier value.
+    fn to_dictionary<K, V, FK, FV>(self, mut key: FK, mut value: FV) -> Result<HashMap<K, V>, DuplicateKeyError<K>>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              FK: FnMut(&Self::Item) -> K,
+              FV: FnMut(Self::Item) -> V
+    {
+        let mut map = HashMap::new();
+        for x in self {
+            match map.entry(key(&x)) {
+                Entry::Occupied(e) => return Err(DuplicateKeyError { key: e.remove_entry().0 }),
+                Entry::Vacant(e) => { e.insert(value(x)); }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Like `to_dictionary`, but a duplicate key overwrites the earlier value.
+    fn to_dictionary_last_wins<K, V, FK, FV>(self, mut key: FK, mut value: FV) -> HashMap<K, V>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              FK: FnMut(&Self::Item) -> K,
+              FV: FnMut(Self::Item) -> V
+    {
+        self.map(|x| (key(&x), value(x))).collect()
+    }
+
+    /// The equivalent of C#'s `DefaultIfEmpty`: yields the sequence unchanged, unless it is
+    /// empty, in which case it yields `default` once.
+    #[inline]
+    fn default_if_empty(self, default: Self::Item) -> DefaultIfEmpty<Self>
+        where Self: Sized
+    {
+        DefaultIfEmpty { iter: self, default: Some(default) }
+    }
+
+    /// The equivalent of C#'s `SequenceEqual`: true if both sequences have the same length
+    /// and equal elements in the same order. Stops at the first difference.
+    #[inline]
+    fn sequence_equal<U>(self, other: U) -> bool
+        where Self: Sized,
+              Self::Item: PartialEq,
+              U: IntoIterator<Item = Self::Item>
+    {
+        self.sequence_equal_by(other, |a, b| a == b)
+    }
+
+    /// Like `sequence_equal`, but uses `eq` to compare the elements.
+    fn sequence_equal_by<U, F>(mut self, other: U, mut eq: F) -> bool
+        where Self: Sized,
+              U: IntoIterator,
+              F: FnMut(&Self::Item, &U::Item) -> bool
+    {
+        let mut other = other.into_iter();
+        loop {
+            match (self.next(), other.next()) {
+                (None, None) => return true,
+                (Some(a), Some(b)) => if !eq(&a, &b) { return false },
+                _ => return false
+            }
+        }
+    }
+
+    /// The equivalent of C#'s `ElementAt`, except that an index past the end of the sequence
+    /// gives `None` rather than an exception. This is std's `nth` under a more familiar name.
+    #[inline]
+    fn element_at(mut self, index: usize) -> Option<Self::Item>
+        where Self: Sized
+    {
+        self.nth(index)
+    }
+
+    /// The equivalent of C#'s `ElementAtOrDefault`, with the default supplied by the caller.
+    #[inline]
+    fn element_at_or(self, index: usize, default: Self::Item) -> Self::Item
+        where Self: Sized
+    {
+        self.element_at(index).unwrap_or(default)
+    }
+
+    /// The equivalent of C#'s `First(predicate)`, except that no match gives `None`. This
+    /// stops reading as soon as it finds a match.
+    #[inline]
+    fn first_where<P>(&mut self, mut predicate: P) -> Option<Self::Item>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.find(|x| predicate(x))
+    }
+
+    #[inline]
+    fn first_where_or<P>(&mut self, predicate: P, default: Self::Item) -> Self::Item
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.first_where(predicate).unwrap_or(default)
+    }
+
+    // These mirror the `first_*` family. std's `last` consumes the iterator, and we can't
+    // call ours `last` because it would clash with it.
+    #[inline]
+    fn last_linq(&mut self) -> Option<Self::Item>
+        where Self: Sized
+    {
+        self.by_ref().last()
+    }
+
+    #[inline]
+    fn last_or(&mut self, default: Self::Item) -> Self::Item
+        where Self: Sized
+    {
+        self.last_linq().unwrap_or(default)
+    }
+
+    #[inline]
+    fn last_or_else<F>(&mut self, f: F) -> Self::Item
+        where Self: Sized,
+              F: FnOnce() -> Self::Item
+    {
+        self.last_linq().unwrap_or_else(f)
+    }
+
+    #[inline]
+    fn last_or_default(&mut self) -> Self::Item
+        where Self: Sized,
+              Self::Item: Default
+    {
+        self.last_or(Default::default())
+    }
+
+    /// The equivalent of C#'s `Count(predicate)`.
+    #[inline]
+    fn count_where<P>(self, mut predicate: P) -> usize
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.filter(|x| predicate(x)).count()
+    }
+
+    /// The equivalent of C#'s `LongCount`, for sequences that might be longer than `usize`
+    /// can count on a 32-bit target.
+    #[inline]
+    fn long_count(self) -> u64
+        where Self: Sized
+    {
+        self.fold(0, |count, _| count + 1)
+    }
+
+    #[inline]
+    fn long_count_where<P>(self, mut predicate: P) -> u64
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.filter(|x| predicate(x)).long_count()
+    }
+
+    /// The equivalent of C#'s `Contains`. Stops reading as soon as it finds the value.
+    /// Note that ranges have an inherent `contains` method which takes precedence over this.
+    #[inline]
+    fn contains(&mut self, value: &Self::Item) -> bool
+        where Self: Sized,
+              Self::Item: PartialEq
+    {
+        self.any(|x| x == *value)
+    }
+
+    /// Like `contains`, but uses `f` to decide whether an element is the one we want.
+    #[inline]
+    fn contains_by<F>(&mut self, mut f: F) -> bool
+        where Self: Sized,
+              F: FnMut(&Self::Item) -> bool
+    {
+        self.any(|x| f(&x))
+    }
+
+    /// The equivalent of C#'s `Reverse`. Unlike std's `rev` this works with any iterator, not
+    /// just a `DoubleEndedIterator`, at the cost of buffering the whole sequence (which is
+    /// done on the first call to `next`).
+    #[inline]
+    fn reverse(self) -> Reverse<Self>
+        where Self: Sized
+    {
+        Reverse { iter: Some(self), items: Vec::new() }
+    }
+
+    /// The equivalent of C#'s `Zip(other, resultSelector)`: combines each pair of elements
+    /// with `f`. Stops at the end of the shorter sequence.
+    #[inline]
+    fn zip_with<U, F, R>(self, other: U, f: F) -> ZipWith<Self, U::IntoIter, F>
+        where Self: Sized,
+              U: IntoIterator,
+              F: FnMut(Self::Item, U::Item) -> R
+    {
+        ZipWith { a: self, b: other.into_iter(), f }
+    }
+
+    /// The equivalent of C#'s `Chunk`: yields `Vec`s of `size` elements, the last of which
+    /// may be shorter. Panics if `size` is zero.
+    #[inline]
+    fn chunk(self, size: usize) -> Chunk<Self>
+        where Self: Sized
+    {
+        assert!(size > 0, "size must be greater than zero");
+        Chunk { iter: self, size }
+    }
+
+    /// Like the `windows` method on slices, but for any iterator: yields each overlapping
+    /// window of `size` consecutive elements. A sequence shorter than `size` yields nothing.
+    /// Panics if `size` is zero.
+    #[inline]
+    fn windows(self, size: usize) -> Windows<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        assert!(size > 0, "size must be greater than zero");
+        Windows { iter: self, size, buffer: VecDeque::with_capacity(size) }
+    }
+
+    /// Yields the running value of a fold, one output per input, e.g. running totals. Unlike
+    /// std's `scan` the closure can't end the iteration early.
+    #[inline]
+    fn scan_linq<A, F>(self, seed: A, f: F) -> Scan<Self, A, F>
+        where Self: Sized,
+              A: Clone,
+              F: FnMut(&A, Self::Item) -> A
+    {
+        Scan { iter: self, acc: seed, f }
+    }
+
+    /// std's `partition` with the collection type fixed to `Vec`: returns the elements that
+    /// satisfy the predicate, then those that don't.
+    #[inline]
+    fn partition_linq<P>(self, mut predicate: P) -> (Vec<Self::Item>, Vec<Self::Item>)
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.partition(|x| predicate(x))
+    }
+
+    /// Splits the sequence three ways according to which `Partition3` each element is
+    /// assigned to, preserving the order within each part.
+    fn partition3<F>(self, mut classify: F) -> ThreeWayPartition<Self::Item>
+        where Self: Sized,
+              F: FnMut(&Self::Item) -> Partition3
+    {
+        let mut left = Vec::new();
+        let mut middle = Vec::new();
+        let mut right = Vec::new();
+
+        for x in self {
+            match classify(&x) {
+                Partition3::Left => left.push(x),
+                Partition3::Middle => middle.push(x),
+                Partition3::Right => right.push(x)
+            }
+        }
+
+        (left, middle, right)
+    }
+
+    /// Yields only the elements whose (zero-based) position is within `range`, which may be
+    /// any kind of range: `2..5`, `2..=5`, `..3`, `4..` etc. This generalizes `skip` plus
+    /// `take`, and like them it stops reading once it is past the end of the range.
+    #[inline]
+    fn in_range<R>(self, range: R) -> InRange<Self>
+        where Self: Sized,
+              R: RangeBounds<usize>
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s.saturating_add(1),
+            Bound::Unbounded => 0
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => Some(e.saturating_add(1)),
+            Bound::Excluded(&e) => Some(e),
+            Bound::Unbounded => None
+        };
+
+        InRange { iter: self, pos: 0, start, end }
+    }
+
+    /// The equivalent of C#'s `TakeLast`: yields only the last `n` elements. The source is
+    /// read on the first call to `next`, keeping at most `n` elements in a ring buffer.
+    #[inline]
+    fn take_last(self, n: usize) -> TakeLast<Self>
+        where Self: Sized
+    {
+        TakeLast { iter: Some(self), n, buffer: VecDeque::with_capacity(n) }
+    }
+
+    /// The equivalent of C#'s `SkipLast`: yields all but the last `n` elements. This streams,
+    /// keeping `n` elements buffered so it knows when it has reached the last `n`.
+    #[inline]
+    fn skip_last(self, n: usize) -> SkipLast<Self>
+        where Self: Sized
+    {
+        SkipLast { iter: self, n, buffer: VecDeque::with_capacity(n) }
+    }
+
+    /// The nearest analogue to C#'s `OfType<T>`: yields only the elements for which
+    /// `extract` returns `Some`, typically used to pick out one variant of an enum.
+    #[inline]
+    fn of_variant<T, F>(self, extract: F) -> OfVariant<Self, F>
+        where Self: Sized,
+              F: FnMut(Self::Item) -> Option<T>
+    {
+        OfVariant { iter: self, extract }
+    }
+
+    /// Like `for_each`, but also passes the zero-based index of each element, saving a
+    /// call to `enumerate`.
+    #[inline]
+    fn for_each_indexed<F>(self, mut f: F)
+        where Self: Sized,
+              F: FnMut(usize, Self::Item)
+    {
+        for (i, x) in self.enumerate() {
+            f(i, x);
+        }
+    }
+
+    /// The equivalent of C#'s `Sum(selector)`: sums the values `f` projects from each
+    /// element, saving a preceding `map`.
+    #[inline]
+    fn sum_by<F, N>(self, f: F) -> N
+        where Self: Sized,
+              F: FnMut(Self::Item) -> N,
+              N: iter::Sum
+    {
+        self.map(f).sum()
+    }
+
+    /// The equivalent of C#'s `Average(selector)`: averages the values `f` projects from each
+    /// element. As with `average`, an empty sequence gives `None`.
+    #[inline]
+    fn average_by<F, N>(self, f: F) -> Option<f64>
+        where Self: Sized,
+              F: FnMut(Self::Item) -> N,
+              N: ToF64
+    {
+        self.map(f).average()
+    }
+
+    /// Yields an element only when it differs from the element yielded just before it,
+    /// collapsing runs of equal values. Unlike `distinct`, the same value can appear again
+    /// later in the output.
+    #[inline]
+    fn distinct_until_changed(self) -> DistinctUntilChanged<Self>
+        where Self: Sized,
+              Self::Item: PartialEq + Clone
+    {
+        DistinctUntilChanged { iter: self, last: None }
+    }
+
+    /// Like `distinct_until_changed`, but compares adjacent elements by the key returned
+    /// by `key`.
+    #[inline]
+    fn distinct_until_changed_by<K, F>(self, key: F) -> DistinctUntilChangedBy<Self, F, K>
+        where Self: Sized,
+              K: PartialEq,
+              F: FnMut(&Self::Item) -> K
+    {
+        DistinctUntilChangedBy { iter: self, key, last_key: None }
+    }
+
+    /// The equivalent of C#'s `CountBy`: counts how many elements fall under each key,
+    /// in a single pass.
+    fn count_by<K, F>(self, mut key: F) -> HashMap<K, usize>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              F: FnMut(&Self::Item) -> K
+    {
+        let mut counts = HashMap::new();
+        for x in self {
+            *counts.entry(key(&x)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// The equivalent of C#'s `SelectMany`: projects each element to a sub-sequence and
+    /// flattens them all into one stream. This is the same as `flat_map`.
+    #[inline]
+    fn select_many<F, U>(self, f: F) -> SelectMany<Self, F, U::IntoIter>
+        where Self: Sized,
+              F: FnMut(Self::Item) -> U,
+              U: IntoIterator
+    {
+        SelectMany { iter: self, f, current: None }
+    }
+
+    /// Like `select_many`, but also passes the zero-based index of each source element to
+    /// the projection.
+    #[inline]
+    fn select_many_indexed<F, U>(self, f: F) -> SelectManyIndexed<Self, F, U::IntoIter>
+        where Self: Sized,
+              F: FnMut(usize, Self::Item) -> U,
+              U: IntoIterator
+    {
+        SelectManyIndexed { iter: self, f, index: 0, current: None }
+    }
+
+    /// The equivalent of C#'s `IntersectBy`: yields the elements whose key appears in
+    /// `other`, which is a sequence of keys. As with `intersect`, each key is only yielded
+    /// once, and `other` is read on the first call to `next`.
+    #[inline]
+    fn intersect_by<K, U, F>(self, other: U, key: F) -> IntersectBy<Self, U::IntoIter, F, K>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              U: IntoIterator<Item = K>,
+              F: FnMut(&Self::Item) -> K
+    {
+        IntersectBy { iter: self, other: Some(other.into_iter()), key, keys: HashSet::new() }
+    }
+
+    /// The equivalent of C#'s `ExceptBy`: yields the elements whose key does not appear in
+    /// `other`, which is a sequence of keys. Each key is only yielded once, and `other` is
+    /// read on the first call to `next`.
+    #[inline]
+    fn except_by<K, U, F>(self, other: U, key: F) -> ExceptBy<Self, U::IntoIter, F, K>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              U: IntoIterator<Item = K>,
+              F: FnMut(&Self::Item) -> K
+    {
+        ExceptBy { iter: self, other: Some(other.into_iter()), key, keys: HashSet::new() }
+    }
+
+    /// The equivalent of C#'s `UnionBy`: yields the elements of this sequence, then those of
+    /// `other`, skipping any whose key has already been seen. Where keys collide the element
+    /// from this sequence wins.
+    #[inline]
+    fn union_by<K, U, F>(self, other: U, key: F) -> UnionBy<Self, U::IntoIter, F, K>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              U: IntoIterator<Item = Self::Item>,
+              F: FnMut(&Self::Item) -> K
+    {
+        UnionBy { a: self, b: other.into_iter(), key, keys: HashSet::new() }
+    }
+
+    /// Forks the sequence into two independent iterators that each yield every element, like
+    /// Python's `itertools.tee`. The source is only read once; elements that one fork has
+    /// seen but the other has not are buffered until the other catches up.
+    fn tee(self) -> (Tee<Self>, Tee<Self>)
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        let buffer = Rc::new(RefCell::new(TeeBuffer { iter: self, items: VecDeque::new(), owner: false }));
+        (Tee { buffer: buffer.clone(), id: false }, Tee { buffer, id: true })
+    }
+
+    /// Yields every `(a, b)` pair from the two sequences, with this sequence in the outer
+    /// loop. `other` is collected into a `Vec` on the first call to `next` so that it can
+    /// be replayed for each element of this sequence.
+    #[inline]
+    fn cartesian<U>(self, other: U) -> CartesianProduct<Self, U::IntoIter>
+        where Self: Sized,
+              Self::Item: Clone,
+              U: IntoIterator,
+              U::Item: Clone
+    {
+        CartesianProduct { a: self, b: Some(other.into_iter()), items: Vec::new(), current: None }
+    }
+
+    /// Yields `(key, elements)` for each run of adjacent elements that share a key. Unlike
+    /// `group_by` this streams, and an element only joins a group if it is next to it, so
+    /// the same key can produce several groups.
+    #[inline]
+    fn group_adjacent<K, F>(self, key: F) -> GroupAdjacent<Self, F, K>
+        where Self: Sized,
+              K: PartialEq,
+              F: FnMut(&Self::Item) -> K
+    {
+        GroupAdjacent { iter: self, key, pending: None }
+    }
+
+    /// Yields `(element, count)` for each run of equal adjacent elements, so
+    /// `[a, a, a, b, a]` becomes `[(a, 3), (b, 1), (a, 1)]`.
+    #[inline]
+    fn run_length_encode(self) -> RunLengthEncode<Self>
+        where Self: Sized,
+              Self::Item: PartialEq
+    {
+        RunLengthEncode { iter: self, pending: None }
+    }
+
+    /// Returns `(min, max)` in a single pass, rather than calling `min` and `max` separately
+    /// which would need the sequence twice. For a single element both are that element.
+    fn min_max(mut self) -> Option<(Self::Item, Self::Item)>
+        where Self: Sized,
+              Self::Item: Ord + Clone
+    {
+        let first = self.next()?;
+        Some(self.fold((first.clone(), first), |(min, max), x| {
+            if x < min {
+                (x, max)
+            } else if x > max {
+                (min, x)
+            } else {
+                (min, max)
+            }
+        }))
+    }
+
+    /// Like `min_max`, but compares elements by the key returned by `key`. As with
+    /// `min_by_key_linq` and `max_by_key_linq`, ties go to the first element.
+    fn min_max_by_key<K, F>(mut self, mut key: F) -> Option<(Self::Item, Self::Item)>
+        where Self: Sized,
+              Self::Item: Clone,
+              K: Ord + Clone,
+              F: FnMut(&Self::Item) -> K
+    {
+        let first = self.next()?;
+        let first_key = key(&first);
+        let init = ((first_key.clone(), first.clone()), (first_key, first));
+        let ((_, min), (_, max)) = self.fold(init, |(min, max), x| {
+            let k = key(&x);
+            if k < min.0 {
+                ((k, x), max)
+            } else if k > max.0 {
+                (min, (k, x))
+            } else {
+                (min, max)
+            }
+        });
+        Some((min, max))
+    }
+
+    /// The element that occurs most often. If several elements are tied, the one that
+    /// appeared first wins.
+    fn mode(self) -> Option<Self::Item>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone
+    {
+        let mut mode = None;
+        for (x, count) in counts_in_order(self) {
+            match mode {
+                Some((_, max)) if count <= max => {},
+                _ => mode = Some((x, count))
+            }
+        }
+        mode.map(|(x, _)| x)
+    }
+
+    /// Like `mode`, but returns every element tied for the most occurrences, in order of
+    /// first appearance. An empty sequence gives an empty `Vec`.
+    fn mode_all(self) -> Vec<Self::Item>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone
+    {
+        let counts = counts_in_order(self);
+        let max = counts.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        counts.into_iter().filter(|&(_, count)| count == max).map(|(x, _)| x).collect()
+    }
+
+    /// The median of the elements: the middle value once sorted, or the mean of the two
+    /// middle values if there is an even number of them. An empty sequence gives `None`.
+    fn median(self) -> Option<f64>
+        where Self: Sized,
+              Self::Item: Into<f64>
+    {
+        let mut values = self.map(Into::into).collect::<Vec<f64>>();
+        if values.is_empty() {
+            return None;
+        }
+
+        values.sort_by(f64::total_cmp);
+        let mid = values.len() / 2;
+        if values.len() % 2 == 1 {
+            Some(values[mid])
+        } else {
+            Some((values[mid - 1] + values[mid]) / 2.0)
+        }
+    }
+
+    /// The value at percentile `p` (from 0 to 100) of the elements, using linear
+    /// interpolation between the closest ranks. An empty sequence gives `None`.
+    ///
+    /// Panics if `p` is outside `0.0..=100.0`.
+    fn percentile(self, p: f64) -> Option<f64>
+        where Self: Sized,
+              Self::Item: Into<f64>
+    {
+        assert!((0.0..=100.0).contains(&p), "p must be between 0 and 100");
+
+        let mut values = self.map(Into::into).collect::<Vec<f64>>();
+        if values.is_empty() {
+            return None;
+        }
+
+        values.sort_by(f64::total_cmp);
+        let rank = p / 100.0 * (values.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        Some(values[lower] + (values[upper] - values[lower]) * (rank - lower as f64))
+    }
+
+    /// Yields the elements in a random order, using a Fisher-Yates shuffle driven by `rng`.
+    /// All the elements are buffered first. Passing in the RNG means a seeded one can be
+    /// used to get a repeatable order.
+    fn shuffle<R: Rng>(self, rng: &mut R) -> Shuffle<Self::Item>
+        where Self: Sized
+    {
+        let mut items = self.collect::<Vec<_>>();
+        for i in (1..items.len()).rev() {
+            let j = rng.gen_range(0..=i);
+            items.swap(i, j);
+        }
+        Shuffle { items: items.into_iter() }
+    }
+
+    /// Draws a uniform random sample of up to `k` elements in a single pass using reservoir
+    /// sampling, so only `k` elements are ever buffered. If there are fewer than `k`
+    /// elements they are all returned.
+    fn sample<R: Rng>(self, k: usize, rng: &mut R) -> Vec<Self::Item>
+        where Self: Sized
+    {
+        let mut reservoir = Vec::with_capacity(k);
+        if k == 0 {
+            return reservoir;
+        }
+
+        for (i, x) in self.enumerate() {
+            if i < k {
+                reservoir.push(x);
+            } else {
+                let j = rng.gen_range(0..=i);
+                if j < k {
+                    reservoir[j] = x;
+                }
+            }
+        }
+
+        reservoir
+    }
+
+    /// Alternates elements from this sequence and `other`, starting with this one. When one
+    /// runs out the rest of the other is yielded. This is `interleave_weighted` with both
+    /// weights set to 1.
+    #[inline]
+    fn interleave<U>(self, other: U) -> Interleave<Self, U::IntoIter>
+        where Self: Sized,
+              U: IntoIterator<Item = Self::Item>
+    {
+        Interleave { a: self, b: other.into_iter(), from_a: true }
+    }
+
+    /// Like SQL's `LAG`: pairs each element with the element `n` positions before it, or
+    /// with `fill` if there isn't one. With `n` of 0 each element is paired with itself.
+    #[inline]
+    fn lag(self, n: usize, fill: Self::Item) -> Lag<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        Lag { iter: self, n, fill, buffer: VecDeque::with_capacity(n + 1) }
+    }
+
+    /// Like SQL's `LEAD`: pairs each element with the element `n` positions after it, or
+    /// with `fill` if there isn't one. Up to `n` elements are read ahead.
+    #[inline]
+    fn lead(self, n: usize, fill: Self::Item) -> Lead<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        Lead { iter: self, n, fill, buffer: VecDeque::with_capacity(n + 1) }
+    }
+
+    /// Makes a single-pass sequence replayable. Every element read from the source is
+    /// buffered in state shared between clones of the returned `Cached`, and a clone always
+    /// starts again from the first element. The source is only ever read once.
+    #[inline]
+    fn cached(self) -> Cached<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        Cached { cache: Rc::new(RefCell::new(Cache { iter: self, items: Vec::new() })), index: 0 }
+    }
+
+    /// Compares two sequences lexicographically: the first pair of elements that differ
+    /// decides the order, and if one sequence is a prefix of the other the shorter one is
+    /// `Less`. Stops at the first difference. This is the ordering sibling of `sequence_equal`.
+    fn sequence_compare<U>(mut self, other: U) -> Ordering
+        where Self: Sized,
+              Self::Item: Ord,
+              U: IntoIterator<Item = Self::Item>
+    {
+        let mut other = other.into_iter();
+        loop {
+            match (self.next(), other.next()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(a), Some(b)) => match a.cmp(&b) {
+                    Ordering::Equal => {},
+                    ordering => return ordering
+                }
+            }
+        }
+    }
+
+    /// Flattens exactly one level of nesting, lazily. This is std's `flatten` under the name
+    /// C# users will look for (C# has no `Flatten`, it is `SelectMany(x => x)`).
+    #[inline]
+    fn flatten_linq(self) -> FlattenLinq<Self>
+        where Self: Sized,
+              Self::Item: IntoIterator
+    {
+        FlattenLinq { iter: self, current: None }
+    }
+
+    /// Flattens a sequence of `Option`s into the values that are present, dropping the
+    /// `None`s. Works because `Option` is itself iterable.
+    #[inline]
+    fn flatten_options<T>(self) -> FlattenLinq<Self>
+        where Self: Sized + Iterator<Item = Option<T>>
+    {
+        self.flatten_linq()
+    }
+
+    /// Yields every `step`th element, starting with the element at index `offset`. This is
+    /// std's `step_by` with the addition of a starting offset.
+    ///
+    /// Panics if `step` is zero.
+    #[inline]
+    fn take_every(self, step: usize, offset: usize) -> TakeEvery<Self>
+        where Self: Sized
+    {
+        assert!(step > 0, "step must be greater than zero");
+        TakeEvery { iter: self, step, skip: offset }
+    }
+
+    /// Zips three sequences into flat `(a, b, c)` triples, stopping at the end of the
+    /// shortest. Chaining std's `zip` twice would give nested `((a, b), c)` tuples instead.
+    #[inline]
+    fn zip3<U, V>(self, second: U, third: V) -> Zip3<Self, U::IntoIter, V::IntoIter>
+        where Self: Sized,
+              U: IntoIterator,
+              V: IntoIterator
+    {
+        Zip3 { a: self, b: second.into_iter(), c: third.into_iter() }
+    }
+
+    /// The zero-based index of the first element equal to `value`, like C#'s `List.IndexOf`
+    /// but for any sequence. Stops at the first match.
+    #[inline]
+    fn index_of(&mut self, value: &Self::Item) -> Option<usize>
+        where Self: Sized,
+              Self::Item: PartialEq
+    {
+        self.position(|x| x == *value)
+    }
+
+    /// The zero-based index of the first element that satisfies `predicate`. Like std's
+    /// `position`, but the predicate takes a reference, as with `first_where`. Stops at the
+    /// first match.
+    #[inline]
+    fn index_of_where<P>(&mut self, mut predicate: P) -> Option<usize>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        self.position(|x| predicate(&x))
+    }
+
+    /// Like `order_by`, but sorts using `comparer` rather than a key, the equivalent of
+    /// passing an `IComparer<T>` to C#'s `OrderBy`.
+    #[inline]
+    fn order_by_comparer<C>(self, comparer: C) -> OrderedEnumerable<Self::Item>
+        where Self: Sized,
+              C: Comparer<Self::Item> + 'static
+    {
+        OrderedEnumerable::new(self.collect(), Box::new(move |a, b| comparer.compare(a, b)))
+    }
+
+    /// The smallest element according to `comparer`. If several elements are equally small
+    /// the first one is returned.
+    fn min_by_comparer<C>(self, comparer: C) -> Option<Self::Item>
+        where Self: Sized,
+              C: Comparer<Self::Item>
+    {
+        self.fold(None, |min, x| match min {
+            Some(m) if comparer.compare(&x, &m) != Ordering::Less => Some(m),
+            _ => Some(x)
+        })
+    }
+
+    /// The largest element according to `comparer`. If several elements are equally large
+    /// the first one is returned.
+    fn max_by_comparer<C>(self, comparer: C) -> Option<Self::Item>
+        where Self: Sized,
+              C: Comparer<Self::Item>
+    {
+        self.fold(None, |max, x| match max {
+            Some(m) if comparer.compare(&x, &m) != Ordering::Greater => Some(m),
+            _ => Some(x)
+        })
+    }
+
+    /// Like `distinct`, but uses `comparer` to decide which elements are equal, the
+    /// equivalent of passing an `IEqualityComparer<T>` to C#'s `Distinct`.
+    #[inline]
+    fn distinct_with<C>(self, comparer: C) -> DistinctWith<Self, C>
+        where Self: Sized,
+              Self::Item: Clone,
+              C: EqualityComparer<Self::Item>
+    {
+        DistinctWith { iter: self, items: ComparerSet::new(comparer) }
+    }
+
+    /// Like `intersect`, but uses `comparer` to decide which elements are equal.
+    #[inline]
+    fn intersect_with<U, C>(self, other: U, comparer: C) -> IntersectWith<Self, U::IntoIter, C>
+        where Self: Sized,
+              U: IntoIterator<Item = Self::Item>,
+              C: EqualityComparer<Self::Item>
+    {
+        IntersectWith { a: self, b: Some(other.into_iter()), items: ComparerSet::new(comparer) }
+    }
+
+    /// Like `except`, but uses `comparer` to decide which elements are equal.
+    #[inline]
+    fn except_with<U, C>(self, other: U, comparer: C) -> ExceptWith<Self, U::IntoIter, C>
+        where Self: Sized,
+              Self::Item: Clone,
+              U: IntoIterator<Item = Self::Item>,
+              C: EqualityComparer<Self::Item>
+    {
+        ExceptWith { a: self, b: Some(other.into_iter()), items: ComparerSet::new(comparer) }
+    }
+
+    /// Like `union`, but uses `comparer` to decide which elements are equal.
+    #[inline]
+    fn union_with<U, C>(self, other: U, comparer: C) -> UnionWith<Self, U::IntoIter, C>
+        where Self: Sized,
+              Self::Item: Clone,
+              U: IntoIterator<Item = Self::Item>,
+              C: EqualityComparer<Self::Item>
+    {
+        UnionWith { a: self, b: other.into_iter(), items: ComparerSet::new(comparer) }
+    }
+
+    /// The equivalent of C#'s `ToHashSet`.
+    #[inline]
+    fn to_hash_set(self) -> HashSet<Self::Item>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq
+    {
+        self.collect()
+    }
+
+    /// Like `to_hash_set`, but the returned set remembers the order in which the elements
+    /// were first seen.
+    fn to_ordered_set(self) -> OrderedSet<Self::Item>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone
+    {
+        let mut set = OrderedSet { items: Vec::new(), set: HashSet::new() };
+        for x in self {
+            if set.set.insert(x.clone()) {
+                set.items.push(x);
+            }
+        }
+        set
+    }
+
+    /// Reads the whole sequence into a `Snapshot`, which can be inspected with `as_slice`
+    /// and `len` as often as you like and is still an iterator. This avoids having to
+    /// rebuild an iterator chain to apply a second terminal operation to it.
+    #[inline]
+    fn to_snapshot(self) -> Snapshot<Self::Item>
+        where Self: Sized
+    {
+        Snapshot { items: self.collect::<Vec<_>>().into_iter() }
+    }
+
+    /// Yields the difference between each element and the one before it, so `[1, 4, 9, 16]`
+    /// gives `[3, 5, 7]`. A sequence of fewer than two elements gives nothing.
+    ///
+    /// The difference is `Sub`'s output, so for unsigned types any decrease overflows:
+    /// it panics in debug builds and wraps in release builds. Convert to a signed type
+    /// first if the sequence can go down.
+    #[inline]
+    fn deltas(self) -> Deltas<Self>
+        where Self: Sized,
+              Self::Item: Copy + Sub<Output = Self::Item>
+    {
+        Deltas { iter: self, previous: None }
+    }
+
+    /// Picks one element at random, with each element's chance of being picked proportional
+    /// to the weight `weight` gives it. This is done in a single pass without buffering.
+    /// An empty sequence, or one whose weights are all zero, gives `None`.
+    ///
+    /// Panics if any weight is negative, infinite or NaN.
+    fn weighted_pick<R, F>(self, mut weight: F, rng: &mut R) -> Option<Self::Item>
+        where Self: Sized,
+              R: Rng,
+              F: FnMut(&Self::Item) -> f64
+    {
+        let mut total = 0.0;
+        let mut picked = None;
+        for x in self {
+            let w = weight(&x);
+            assert!(w.is_finite() && w >= 0.0, "weights must be finite and not negative");
+            if w == 0.0 {
+                continue;
+            }
+
+            // Replacing the current pick with probability w / total leaves every element
+            // seen so far with a chance proportional to its weight.
+            total += w;
+            if rng.gen::<f64>() * total < w {
+                picked = Some(x);
+            }
+        }
+        picked
+    }
+
+    /// The `n` largest elements, largest first. Only `n` elements are held at a time, in a
+    /// binary heap, so this is cheaper than sorting the whole sequence and taking `n`.
+    fn max_n(self, n: usize) -> Vec<Self::Item>
+        where Self: Sized,
+              Self::Item: Ord
+    {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        // A min-heap, so the smallest of the elements kept so far is the one evicted.
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+        for x in self {
+            heap.push(cmp::Reverse(x));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec().into_iter().map(|r| r.0).collect()
+    }
+
+    /// The `n` smallest elements, smallest first. Like `max_n`, only `n` elements are held
+    /// at a time.
+    fn min_n(self, n: usize) -> Vec<Self::Item>
+        where Self: Sized,
+              Self::Item: Ord
+    {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+        for x in self {
+            heap.push(x);
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+    }
+
+    /// The `n` elements with the largest keys, largest first. Elements with equal keys keep
+    /// their original order.
+    #[inline]
+    fn max_n_by_key<K, F>(self, n: usize, key: F) -> Vec<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: FnMut(&Self::Item) -> K
+    {
+        top_n_by_key(self, n, key)
+    }
+
+    /// The `n` elements with the smallest keys, smallest first. Elements with equal keys
+    /// keep their original order.
+    #[inline]
+    fn min_n_by_key<K, F>(self, n: usize, mut key: F) -> Vec<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: FnMut(&Self::Item) -> K
+    {
+        top_n_by_key(self, n, |x| cmp::Reverse(key(x)))
+    }
+
+    /// True if the sequence has at least `n` elements. Stops after reading `n` elements,
+    /// so unlike `count() >= n` this works on very long or infinite sequences.
+    #[inline]
+    fn at_least(&mut self, n: usize) -> bool
+        where Self: Sized
+    {
+        self.take(n).count() == n
+    }
+
+    /// True if the sequence has no more than `n` elements. Stops after reading `n + 1`
+    /// elements. Always true for `usize::MAX`.
+    #[inline]
+    fn at_most(&mut self, n: usize) -> bool
+        where Self: Sized
+    {
+        match n.checked_add(1) {
+            Some(limit) => self.take(limit).count() <= n,
+            None => true
+        }
+    }
+
+    /// True if the sequence has exactly `n` elements. Stops after reading `n + 1`
+    /// elements.
+    #[inline]
+    fn exactly(&mut self, n: usize) -> bool
+        where Self: Sized
+    {
+        match n.checked_add(1) {
+            Some(limit) => self.take(limit).count() == n,
+            None => self.at_least(n) && self.next().is_none()
+        }
+    }
+
+    /// The equivalent of C#'s `GroupBy(key, seed, func)` overload: folds the elements of
+    /// each group into an accumulator as they are read, without building a list of the
+    /// elements in each group. Each accumulator starts as the value returned by `seed`.
+    fn group_by_aggregate<K, A, FK, FSeed, FAcc>(self, mut key: FK, mut seed: FSeed, mut acc: FAcc) -> HashMap<K, A>
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              FK: FnMut(&Self::Item) -> K,
+              FSeed: FnMut() -> A,
+              FAcc: FnMut(A, Self::Item) -> A
+    {
+        let mut groups = HashMap::new();
+        for x in self {
+            let k = key(&x);
+            let current = groups.remove(&k).unwrap_or_else(&mut seed);
+            groups.insert(k, acc(current, x));
+        }
+        groups
+    }
+
+    /// Like `enumerate`, but the indexes start at `start` rather than 0, which is handy for
+    /// 1-based display numbering.
+    #[inline]
+    fn enumerate_from(self, start: usize) -> EnumerateFrom<Self>
+        where Self: Sized
+    {
+        EnumerateFrom { iter: self, index: start }
+    }
+
+    /// Yields `Vec`s of adjacent elements, starting a new chunk whenever `should_split`,
+    /// given the previous and current elements, returns true. For example splitting into
+    /// ascending runs with `|a, b| b < a`.
+    #[inline]
+    fn chunk_by<P>(self, should_split: P) -> ChunkBy<Self, P>
+        where Self: Sized,
+              P: FnMut(&Self::Item, &Self::Item) -> bool
+    {
+        ChunkBy { iter: self, should_split, pending: None }
+    }
+
+    /// Collapses runs of equal adjacent elements, yielding each with the length of its run.
+    /// This is the same as `run_length_encode`; see `dedup_with_count_by` for comparing
+    /// elements by key.
+    #[inline]
+    fn dedup_with_count(self) -> RunLengthEncode<Self>
+        where Self: Sized,
+              Self::Item: PartialEq
+    {
+        self.run_length_encode()
+    }
+
+    /// Like `dedup_with_count`, but elements are equal if `key` returns the same key for
+    /// them. The first element of each run is the one yielded.
+    #[inline]
+    fn dedup_with_count_by<K, F>(self, key: F) -> DedupWithCountBy<Self, F, K>
+        where Self: Sized,
+              K: PartialEq,
+              F: FnMut(&Self::Item) -> K
+    {
+        DedupWithCountBy { iter: self, key, pending: None }
+    }
+
+    /// The multiplicative counterpart of `sum_linq`. It can't be called `product` because
+    /// that would clash with `Iterator::product`. An empty sequence gives 1.
+    #[inline]
+    fn product_linq(self) -> Self::Item
+        where Self: Sized,
+              Self::Item: iter::Product
+    {
+        self.product()
+    }
+
+    /// Multiplies together the values `f` projects from each element, saving a preceding
+    /// `map`. An empty sequence gives 1.
+    #[inline]
+    fn product_by<F, N>(self, f: F) -> N
+        where Self: Sized,
+              F: FnMut(Self::Item) -> N,
+              N: iter::Product
+    {
+        self.map(f).product()
+    }
+
+    /// Yields the running total of the elements, so `[1, 2, 3]` gives `[1, 3, 6]`. A
+    /// shortcut for the most common use of `scan_linq`.
+    #[inline]
+    fn cumulative_sum(self) -> CumulativeSum<Self>
+        where Self: Sized,
+              Self::Item: Copy + Add<Output = Self::Item>
+    {
+        CumulativeSum { iter: self, total: None }
+    }
+
+    /// Yields the largest element seen so far, so `[1, 3, 2, 5]` gives `[1, 3, 3, 5]`.
+    #[inline]
+    fn cumulative_max(self) -> CumulativeMax<Self>
+        where Self: Sized,
+              Self::Item: Ord + Clone
+    {
+        CumulativeMax { iter: self, max: None }
+    }
+
+    /// Skips elements until `predicate` first returns true, then yields that element and
+    /// everything after it. The opposite sense of `skip_while`.
+    #[inline]
+    fn skip_until<P>(self, predicate: P) -> SkipUntil<Self, P>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        SkipUntil { iter: self, predicate, done: false }
+    }
+
+    /// Yields elements until `predicate` first returns true, including the element that
+    /// made it true, then stops. Unlike `take_while`, the triggering element is not lost.
+    #[inline]
+    fn take_until<P>(self, predicate: P) -> TakeUntil<Self, P>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        TakeUntil { iter: self, predicate, done: false }
+    }
+
+    /// Sorts by each of `keys` in turn, the first being the most significant, for when the
+    /// keys are only known at runtime (e.g. from a list of columns chosen by the user) and
+    /// so can't be written as a chain of `then_by` calls. All the keys must be of the same
+    /// type. The sort is stable, so an empty list of keys leaves the order unchanged.
+    fn order_by_keys<K, F>(self, keys: Vec<F>) -> OrderedEnumerable<Self::Item>
+        where Self: Sized,
+              K: Ord,
+              F: Fn(&Self::Item) -> K + 'static
+    {
+        OrderedEnumerable::new(self.collect(), Box::new(move |a, b| {
+            keys.iter()
+                .map(|key| key(a).cmp(&key(b)))
+                .find(|&ordering| ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        }))
+    }
+
+    /// Sorts the elements and pairs each with its 1-based rank, like SQL's `RANK()`. Equal
+    /// elements share a rank and the following rank is skipped, so `[10, 20, 20, 30]` are
+    /// ranked `[1, 2, 2, 4]`.
+    fn rank(self) -> Vec<(usize, Self::Item)>
+        where Self: Sized,
+              Self::Item: Ord
+    {
+        let mut items = self.collect::<Vec<_>>();
+        items.sort();
+
+        let mut ranked: Vec<(usize, Self::Item)> = Vec::with_capacity(items.len());
+        for (i, x) in items.into_iter().enumerate() {
+            let rank = match ranked.last() {
+                Some(&(r, ref previous)) if *previous == x => r,
+                _ => i + 1
+            };
+            ranked.push((rank, x));
+        }
+        ranked
+    }
+
+    /// Like `rank`, but the rank after a tie is not skipped, like SQL's `DENSE_RANK()`, so
+    /// `[10, 20, 20, 30]` are ranked `[1, 2, 2, 3]`.
+    fn dense_rank(self) -> Vec<(usize, Self::Item)>
+        where Self: Sized,
+              Self::Item: Ord
+    {
+        let mut items = self.collect::<Vec<_>>();
+        items.sort();
+
+        let mut ranked: Vec<(usize, Self::Item)> = Vec::with_capacity(items.len());
+        for x in items {
+            let rank = match ranked.last() {
+                Some(&(r, ref previous)) if *previous == x => r,
+                Some(&(r, _)) => r + 1,
+                None => 1
+            };
+            ranked.push((rank, x));
+        }
+        ranked
+    }
+
+    /// Like `aggregate_with`, but `f` can fail, in which case the fold stops and the error
+    /// is returned. This is std's `try_fold` with the result type fixed to `Result`.
+    #[inline]
+    fn try_aggregate<A, E, F>(mut self, seed: A, f: F) -> Result<A, E>
+        where Self: Sized,
+              F: FnMut(A, Self::Item) -> Result<A, E>
+    {
+        self.try_fold(seed, f)
+    }
+
+    /// Collects the first `index` elements into a `Vec` and returns them along with the
+    /// rest of the sequence, which has not been read. Useful for peeling off a header and
+    /// then streaming the body.
+    #[inline]
+    fn split_at_linq(mut self, index: usize) -> (Vec<Self::Item>, Self)
+        where Self: Sized
+    {
+        let prefix = self.by_ref().take(index).collect();
+        (prefix, self)
+    }
+
+    /// Reduces each sliding window of `size` elements to a single value with `f`, e.g. a
+    /// moving sum or moving average. This is `map_windows` under the name used for it in
+    /// analytics code. Panics if `size` is zero.
+    #[inline]
+    fn rolling<A, F>(self, size: usize, f: F) -> MapWindows<Self, F>
+        where Self: Sized,
+              F: FnMut(&[Self::Item]) -> A
+    {
+        // Called through the trait to avoid the unstable `Iterator::map_windows`.
+        LinqIteratorExtensions::map_windows(self, size, f)
+    }
+
+    /// Folds from the last element to the first, for right-associative combinations. Note
+    /// that `f` takes the element first and the accumulator second. The elements have to
+    /// be buffered first, since most iterators can only go forwards.
+    fn aggregate_right<A, F>(self, seed: A, mut f: F) -> A
+        where Self: Sized,
+              F: FnMut(Self::Item, A) -> A
+    {
+        let items = self.collect::<Vec<_>>();
+        items.into_iter().rev().fold(seed, |acc, x| f(x, acc))
+    }
+
+    /// Yields each run of `n` consecutive characters as a `String`, so the characters of
+    /// "hello" with `n` of 2 give "he", "el", "ll", "lo". Panics if `n` is zero.
+    #[inline]
+    fn ngrams(self, n: usize) -> NGrams<Self>
+        where Self: Sized + Iterator<Item = char>
+    {
+        assert!(n > 0, "n must be greater than zero");
+        NGrams { iter: self, n, buffer: VecDeque::with_capacity(n) }
+    }
+
+    /// The first element that is equal to an element before it, or `None` if all the
+    /// elements are distinct. Stops as soon as a repeat is found.
+    fn first_duplicate(&mut self) -> Option<Self::Item>
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq + Clone
+    {
+        let mut seen = HashSet::new();
+        self.find(|x| !seen.insert(x.clone()))
+    }
+
+    /// Turns a sequence of rows into a `Vec` of columns, so `[[1, 2, 3], [4, 5, 6]]` becomes
+    /// `[[1, 4], [2, 5], [3, 6]]`. If the rows are ragged the result is truncated to the
+    /// length of the shortest row, in the same way that `zip` stops at the shortest input.
+    fn transpose_rows<T>(self) -> Vec<Vec<T>>
+        where Self: Sized + Iterator<Item = Vec<T>>
+    {
+        let mut rows = self.map(|row| row.into_iter()).collect::<Vec<_>>();
+        let mut columns = Vec::new();
+        if rows.is_empty() {
+            return columns;
+        }
+
+        // Collecting into an `Option` gives `None` as soon as any row runs out.
+        while let Some(column) = rows.iter_mut().map(|row| row.next()).collect::<Option<Vec<_>>>() {
+            columns.push(column);
+        }
+        columns
+    }
+
+    /// Yields the segments between elements that satisfy `is_delim`, dropping the
+    /// delimiters, like `str::split` does for strings. As with `str::split`, leading,
+    /// trailing and adjacent delimiters give empty segments, and an empty sequence gives
+    /// one empty segment.
+    #[inline]
+    fn split_on<P>(self, is_delim: P) -> SplitOn<Self, P>
+        where Self: Sized,
+              P: FnMut(&Self::Item) -> bool
+    {
+        SplitOn { iter: self, is_delim, done: false }
+    }
+
+    /// The equivalent of C#'s `ToList`. The `Vec` is allocated up front using the lower bound
+    /// of the size hint, which for an `ExactSizeIterator` is the exact length.
+    #[inline]
+    fn to_list(self) -> Vec<Self::Item>
+        where Self: Sized
+    {
+        let mut list = Vec::with_capacity(self.size_hint().0);
+        list.extend(self);
+        list
+    }
+
+    /// The equivalent of C#'s `ToArray`. A boxed slice is used, rather than a `Vec`, to show
+    /// that the result is not going to grow. See `to_fixed_array` for a true array.
+    #[inline]
+    fn to_array(self) -> Box<[Self::Item]>
+        where Self: Sized
+    {
+        self.to_list().into_boxed_slice()
+    }
+
+    /// Merges this sequence and `other`, both of which must already be sorted ascending,
+    /// into one sorted sequence. This is lazy and much cheaper than sorting the two chained
+    /// together. The merge is stable: on ties the element from this sequence comes first.
+    #[inline]
+    fn merge_sorted<U>(self, other: U) -> MergeSorted<Self, U::IntoIter>
+        where Self: Sized,
+              Self::Item: Ord,
+              U: IntoIterator<Item = Self::Item>
+    {
+        MergeSorted { a: self.peekable(), b: other.into_iter().peekable() }
+    }
+
+    /// Like `merge_sorted`, but the order of the two input sequences, and of the output, is
+    /// given by `compare`.
+    #[inline]
+    fn merge_sorted_by<U, F>(self, other: U, compare: F) -> MergeSortedBy<Self, U::IntoIter, F>
+        where Self: Sized,
+              U: IntoIterator<Item = Self::Item>,
+              F: FnMut(&Self::Item, &Self::Item) -> Ordering
+    {
+        MergeSortedBy { a: self.peekable(), b: other.into_iter().peekable(), compare }
+    }
+
+    /// Yields the whole sequence `times` times over, so `[1, 2]` repeated 3 times gives
+    /// `[1, 2, 1, 2, 1, 2]`. Unlike `cycle` this stops, and the source is only read once:
+    /// elements are buffered on the first pass and replayed from the buffer after that.
+    #[inline]
+    fn repeat_sequence(self, times: usize) -> RepeatSequence<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        RepeatSequence {
+            iter: if times == 0 { None } else { Some(self) },
+            items: Vec::new(),
+            replays: times.saturating_sub(1),
+            index: 0
+        }
+    }
+
+    /// Replaces each `None` in a sequence of `Option`s with `fallback`, yielding the plain
+    /// values. The equivalent of applying C#'s `??` operator to every element.
+    #[inline]
+    fn coalesce<T>(self, fallback: T) -> Coalesce<Self, T>
+        where Self: Sized + Iterator<Item = Option<T>>,
+              T: Clone
+    {
+        Coalesce { iter: self, fallback }
+    }
+
+    /// Reduces each run of `n` elements to a single value with `f`, e.g. averaging every 4
+    /// samples to downsample a stream. A partial run at the end is reduced too. This is
+    /// `chunk(n)` followed by a `map`, but without allocating a `Vec` for every run.
+    /// Panics if `n` is zero.
+    #[inline]
+    fn every_nth_reduce<A, F>(self, n: usize, f: F) -> EveryNthReduce<Self, F>
+        where Self: Sized,
+              F: FnMut(&[Self::Item]) -> A
+    {
+        assert!(n > 0, "n must be greater than zero");
+        EveryNthReduce { iter: self, n, f, buffer: Vec::with_capacity(n) }
+    }
+
+    /// Yields the sequence followed by as many copies of `fill` as are needed to make it
+    /// at least `min_len` elements long.
+    #[inline]
+    fn pad_right(self, min_len: usize, fill: Self::Item) -> PadRight<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        PadRight { iter: self, min_len, fill, count: 0 }
+    }
+
+    /// Yields as many copies of `fill` as are needed to make the sequence at least `min_len`
+    /// elements long, followed by the sequence. To know how many to add, the whole
+    /// sequence is buffered on the first call to `next`.
+    #[inline]
+    fn pad_left(self, min_len: usize, fill: Self::Item) -> PadLeft<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        PadLeft { iter: Some(self), min_len, fill, padding: 0, items: Vec::new().into_iter() }
+    }
+
+    /// A fallible `scan_linq`: yields `Ok` with each successive accumulator until `f` fails,
+    /// then yields that `Err` and stops. Partial results can be streamed while still
+    /// finding out about the first error.
+    #[inline]
+    fn scan_try<A, E, F>(self, seed: A, f: F) -> ScanTry<Self, A, F>
+        where Self: Sized,
+              A: Clone,
+              F: FnMut(&A, Self::Item) -> Result<A, E>
+    {
+        ScanTry { iter: self, acc: Some(seed), f }
+    }
+
+    /// The number of distinct elements, the equivalent of C#'s `Distinct().Count()`. This
+    /// reads the whole sequence.
+    #[inline]
+    fn count_distinct(&mut self) -> usize
+        where Self: Sized,
+              Self::Item: hash::Hash + Eq
+    {
+        self.collect::<HashSet<_>>().len()
+    }
+
+    /// The number of distinct keys returned by `key`. This reads the whole sequence.
+    #[inline]
+    fn count_distinct_by<K, F>(&mut self, mut key: F) -> usize
+        where Self: Sized,
+              K: hash::Hash + Eq,
+              F: FnMut(&Self::Item) -> K
+    {
+        self.map(|x| key(&x)).collect::<HashSet<_>>().len()
+    }
+
+    /// Yields the elements with a separator produced by `sep` between each adjacent pair,
+    /// but not before the first or after the last. The iterator equivalent of `join`, and
+    /// the same as the (currently unstable) `Iterator::intersperse_with` in std.
+    #[inline]
+    fn intersperse_with<F>(self, sep: F) -> IntersperseWith<Self, F>
+        where Self: Sized,
+              F: FnMut() -> Self::Item
+    {
+        IntersperseWith { iter: self.peekable(), sep, needs_sep: false }
+    }
+
+    /// Like `intersperse_with`, but the separator is a clone of `sep`. This is the same as
+    /// the (currently unstable) `Iterator::intersperse` in std.
+    #[inline]
+    fn intersperse(self, sep: Self::Item) -> Intersperse<Self>
+        where Self: Sized,
+              Self::Item: Clone
+    {
+        Intersperse { iter: self.peekable(), sep, needs_sep: false }
+    }
+}
+
+
+// Step 2: Use that trait to add extension methods to the Iterator trait.
+// The impl block itself is empty because we defaulted all the method definitions
+// in the trait definition itself.
+impl<T> LinqIteratorExtensions for T where T: Iterator { }
+
+
+// Step 3: Define the structs required by our adapters, if any: these are only required if the
+// adapters need to manage state. n.b. Step 3 implies you need step 4 as well.
+pub struct Intersect<A, B>
+    where A: Iterator,
+          B: Iterator
+{
+    a: A,
+    // Drained into `items` on the first call to `next`.
+    b: Option<B>,
+    items: HashSet<A::Item>
+}
+
+// Step 4: Implement Iterator functionality for our structs.
+impl<A, B> Iterator for Intersect<A, B>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>,
+          A::Item: Eq + hash::Hash
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        if let Some(b) = self.b.take() {
+            self.items.extend(b);
+        }
+
+        // Removing each item as we yield it means duplicates in `a` are only yielded once.
+        let items = &mut self.items;
+        self.a.by_ref().find(|x| items.remove(x))
+    }
+}
+
+pub struct Except<A, B>
+    where A: Iterator,
+          B: Iterator
+{
+    a: A,
+    // Drained into `items` on the first call to `next`.
+    b: Option<B>,
+    items: HashSet<A::Item>
+}
+
+impl<A, B> Iterator for Except<A, B>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>,
+          A::Item: Eq + hash::Hash + Clone
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        if let Some(b) = self.b.take() {
+            self.items.extend(b);
+        }
+
+        // Adding each item as we yield it means duplicates in `a` are only yielded once.
+        let items = &mut self.items;
+        self.a.by_ref().find(|x| items.insert(x.clone()))
+    }
+}
+
+pub struct Union<A, B>
+    where A: Iterator,
+          B: Iterator
+{
+    a: A,
+    b: B,
+    items: HashSet<A::Item>
+}
+
+impl<A, B> Iterator for Union<A, B>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>,
+          A::Item: Eq + hash::Hash + Clone
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        let items = &mut self.items;
+        match self.a.by_ref().find(|x| items.insert(x.clone())) {
+            Some(x) => Some(x),
+            None => self.b.by_ref().find(|x| items.insert(x.clone()))
+        }
+    }
+}
+
+pub struct Distinct<I>
+    where I: Iterator
+{
+    iter: I,
+    items: HashSet<I::Item>
+}
+
+impl<I> Iterator for Distinct<I>
+    where I: Iterator,
+          I::Item: Eq + hash::Hash + Clone
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let items = &mut self.items;
+        self.iter.by_ref().find(|x| items.insert(x.clone()))
+    }
+}
+
+pub struct DistinctBy<I, F, K> {
+    iter: I,
+    key: F,
+    keys: HashSet<K>
+}
+
+impl<I, F, K> Iterator for DistinctBy<I, F, K>
+    where I: Iterator,
+          K: Eq + hash::Hash,
+          F: FnMut(&I::Item) -> K
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let keys = &mut self.keys;
+        let key = &mut self.key;
+        self.iter.by_ref().find(|x| keys.insert(key(x)))
+    }
+}
+
+pub struct IntersectBy<I, J, F, K> {
+    iter: I,
+    // Drained into `keys` on the first call to `next`.
+    other: Option<J>,
+    key: F,
+    keys: HashSet<K>
+}
+
+impl<I, J, F, K> Iterator for IntersectBy<I, J, F, K>
+    where I: Iterator,
+          J: Iterator<Item = K>,
+          K: hash::Hash + Eq,
+          F: FnMut(&I::Item) -> K
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if let Some(other) = self.other.take() {
+            self.keys.extend(other);
+        }
+
+        let keys = &mut self.keys;
+        let key = &mut self.key;
+        self.iter.by_ref().find(|x| keys.remove(&key(x)))
+    }
+}
+
+pub struct ExceptBy<I, J, F, K> {
+    iter: I,
+    // Drained into `keys` on the first call to `next`.
+    other: Option<J>,
+    key: F,
+    keys: HashSet<K>
+}
+
+impl<I, J, F, K> Iterator for ExceptBy<I, J, F, K>
+    where I: Iterator,
+          J: Iterator<Item = K>,
+          K: hash::Hash + Eq,
+          F: FnMut(&I::Item) -> K
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if let Some(other) = self.other.take() {
+            self.keys.extend(other);
+        }
+
+        let keys = &mut self.keys;
+        let key = &mut self.key;
+        self.iter.by_ref().find(|x| keys.insert(key(x)))
+    }
+}
+
+pub struct UnionBy<A, B, F, K> {
+    a: A,
+    b: B,
+    key: F,
+    keys: HashSet<K>
+}
+
+impl<A, B, F, K> Iterator for UnionBy<A, B, F, K>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>,
+          K: hash::Hash + Eq,
+          F: FnMut(&A::Item) -> K
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        let keys = &mut self.keys;
+        let key = &mut self.key;
+        match self.a.by_ref().find(|x| keys.insert(key(x))) {
+            Some(x) => Some(x),
+            None => self.b.by_ref().find(|x| keys.insert(key(x)))
+        }
+    }
+}
+
+pub struct DistinctWith<I, C>
+    where I: Iterator
+{
+    iter: I,
+    items: ComparerSet<I::Item, C>
+}
+
+impl<I, C> Iterator for DistinctWith<I, C>
+    where I: Iterator,
+          I::Item: Clone,
+          C: EqualityComparer<I::Item>
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let items = &mut self.items;
+        self.iter.by_ref().find(|x| items.insert(x.clone()))
+    }
+}
+
+pub struct IntersectWith<A, B, C>
+    where A: Iterator
+{
+    a: A,
+    // Drained into `items` on the first call to `next`.
+    b: Option<B>,
+    items: ComparerSet<A::Item, C>
+}
+
+impl<A, B, C> Iterator for IntersectWith<A, B, C>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>,
+          C: EqualityComparer<A::Item>
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        if let Some(b) = self.b.take() {
+            for x in b {
+                self.items.insert(x);
+            }
+        }
+
+        let items = &mut self.items;
+        self.a.by_ref().find(|x| items.remove(x))
+    }
+}
+
+pub struct ExceptWith<A, B, C>
+    where A: Iterator
+{
+    a: A,
+    // Drained into `items` on the first call to `next`.
+    b: Option<B>,
+    items: ComparerSet<A::Item, C>
+}
+
+impl<A, B, C> Iterator for ExceptWith<A, B, C>
+    where A: Iterator,
+          A::Item: Clone,
+          B: Iterator<Item = A::Item>,
+          C: EqualityComparer<A::Item>
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        if let Some(b) = self.b.take() {
+            for x in b {
+                self.items.insert(x);
+            }
+        }
+
+        let items = &mut self.items;
+        self.a.by_ref().find(|x| items.insert(x.clone()))
+    }
+}
+
+pub struct UnionWith<A, B, C>
+    where A: Iterator
+{
+    a: A,
+    b: B,
+    items: ComparerSet<A::Item, C>
+}
+
+impl<A, B, C> Iterator for UnionWith<A, B, C>
+    where A: Iterator,
+          A::Item: Clone,
+          B: Iterator<Item = A::Item>,
+          C: EqualityComparer<A::Item>
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        let items = &mut self.items;
+        match self.a.by_ref().find(|x| items.insert(x.clone())) {
+            Some(x) => Some(x),
+            None => self.b.by_ref().find(|x| items.insert(x.clone()))
+        }
+    }
+}
+
+/// The reason a `try_single_where` call failed to find exactly one element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SingleError {
+    NoMatch,
+    MultipleMatches
+}
+
+impl fmt::Display for SingleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SingleError::NoMatch => write!(f, "no element matched the predicate"),
+            SingleError::MultipleMatches => write!(f, "more than one element matched the predicate")
+        }
+    }
+}
+
+impl error::Error for SingleError { }
+
+/// A comparison function that can be chosen at runtime, as used by `order_by_many`.
+pub type BoxedComparer<T> = Box<dyn Fn(&T, &T) -> Ordering>;
+
+pub struct OrderByMany<T> {
+    items: vec::IntoIter<T>
+}
+
+impl<T> Iterator for OrderByMany<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.items.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+pub struct SkipWhileCounting<I>
+    where I: Iterator
+{
+    first: Option<I::Item>,
+    iter: I
+}
+
+impl<I> Iterator for SkipWhileCounting<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        match self.first.take() {
+            Some(x) => Some(x),
+            None => self.iter.next()
+        }
+    }
+}
+
+pub struct ReduceAdjacentBy<I, K, KF, RF>
+    where I: Iterator
+{
+    iter: I,
+    key_selector: KF,
+    reduce: RF,
+    pending: Option<(K, I::Item)>
+}
+
+impl<I, K, KF, RF> Iterator for ReduceAdjacentBy<I, K, KF, RF>
+    where I: Iterator,
+          K: PartialEq,
+          KF: FnMut(&I::Item) -> K,
+          RF: FnMut(I::Item, I::Item) -> I::Item
+{
+    type Item = (K, I::Item);
+
+    fn next(&mut self) -> Option<(K, I::Item)> {
+        let (key, mut acc) = match self.pending.take() {
+            Some(p) => p,
+            None => {
+                let x = self.iter.next()?;
+                ((self.key_selector)(&x), x)
+            }
+        };
+
+        for x in self.iter.by_ref() {
+            let k = (self.key_selector)(&x);
+            if k == key {
+                acc = (self.reduce)(acc, x);
+            } else {
+                self.pending = Some((k, x));
+                break;
+            }
+        }
+
+        Some((key, acc))
+    }
+}
+
+pub struct GroupAdjacent<I, F, K>
+    where I: Iterator
+{
+    iter: I,
+    key: F,
+    pending: Option<(K, I::Item)>
+}
+
+impl<I, F, K> Iterator for GroupAdjacent<I, F, K>
+    where I: Iterator,
+          K: PartialEq,
+          F: FnMut(&I::Item) -> K
+{
+    type Item = (K, Vec<I::Item>);
+
+    fn next(&mut self) -> Option<(K, Vec<I::Item>)> {
+        let (key, first) = match self.pending.take() {
+            Some(p) => p,
+            None => {
+                let x = self.iter.next()?;
+                ((self.key)(&x), x)
+            }
+        };
+
+        let mut group = vec![first];
+        for x in self.iter.by_ref() {
+            let k = (self.key)(&x);
+            if k == key {
+                group.push(x);
+            } else {
+                self.pending = Some((k, x));
+                break;
+            }
+        }
+
+        Some((key, group))
+    }
+}
+
+pub struct RunLengthEncode<I>
+    where I: Iterator
+{
+    iter: I,
+    pending: Option<I::Item>
+}
+
+impl<I> Iterator for RunLengthEncode<I>
+    where I: Iterator,
+          I::Item: PartialEq
+{
+    type Item = (I::Item, usize);
+
+    fn next(&mut self) -> Option<(I::Item, usize)> {
+        let value = match self.pending.take() {
+            Some(x) => x,
+            None => self.iter.next()?
+        };
+
+        let mut count = 1;
+        for x in self.iter.by_ref() {
+            if x == value {
+                count += 1;
+            } else {
+                self.pending = Some(x);
+                break;
+            }
+        }
+
+        Some((value, count))
+    }
+}
+
+pub struct DedupWithCountBy<I, F, K>
+    where I: Iterator
+{
+    iter: I,
+    key: F,
+    pending: Option<(K, I::Item)>
+}
+
+impl<I, F, K> Iterator for DedupWithCountBy<I, F, K>
+    where I: Iterator,
+          K: PartialEq,
+          F: FnMut(&I::Item) -> K
+{
+    type Item = (I::Item, usize);
+
+    fn next(&mut self) -> Option<(I::Item, usize)> {
+        let (key, first) = match self.pending.take() {
+            Some(p) => p,
+            None => {
+                let x = self.iter.next()?;
+                ((self.key)(&x), x)
+            }
+        };
+
+        let mut count = 1;
+        for x in self.iter.by_ref() {
+            let k = (self.key)(&x);
+            if k == key {
+                count += 1;
+            } else {
+                self.pending = Some((k, x));
+                break;
+            }
+        }
+
+        Some((first, count))
+    }
+}
+
+pub struct ChunkBy<I, P>
+    where I: Iterator
+{
+    iter: I,
+    should_split: P,
+    pending: Option<I::Item>
+}
+
+impl<I, P> Iterator for ChunkBy<I, P>
+    where I: Iterator,
+          P: FnMut(&I::Item, &I::Item) -> bool
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        let first = match self.pending.take() {
+            Some(x) => x,
+            None => self.iter.next()?
+        };
+
+        let mut chunk = vec![first];
+        for x in self.iter.by_ref() {
+            if (self.should_split)(chunk.last().unwrap(), &x) {
+                self.pending = Some(x);
+                break;
+            }
+            chunk.push(x);
+        }
+
+        Some(chunk)
+    }
+}
+
+pub struct SplitOn<I, P> {
+    iter: I,
+    is_delim: P,
+    done: bool
+}
+
+impl<I, P> Iterator for SplitOn<I, P>
+    where I: Iterator,
+          P: FnMut(&I::Item) -> bool
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        if self.done {
+            return None;
+        }
+
+        let mut segment = Vec::new();
+        for x in self.iter.by_ref() {
+            if (self.is_delim)(&x) {
+                return Some(segment);
+            }
+            segment.push(x);
+        }
+
+        self.done = true;
+        Some(segment)
+    }
+}
+
+pub struct MapWindows<I, F>
+    where I: Iterator
+{
+    iter: I,
+    size: usize,
+    f: F,
+    buffer: VecDeque<I::Item>
+}
+
+impl<I, F, R> Iterator for MapWindows<I, F>
+    where I: Iterator,
+          F: FnMut(&[I::Item]) -> R
+{
+    type Item = R;
+
+    fn next(&mut self) -> Option<R> {
+        if self.buffer.len() == self.size {
+            self.buffer.pop_front();
+        }
+
+        while self.buffer.len() < self.size {
+            self.buffer.push_back(self.iter.next()?);
+        }
+
+        Some((self.f)(self.buffer.make_contiguous()))
+    }
+}
+
+pub struct NGrams<I> {
+    iter: I,
+    n: usize,
+    buffer: VecDeque<char>
+}
+
+impl<I> Iterator for NGrams<I>
+    where I: Iterator<Item = char>
+{
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.buffer.len() == self.n {
+            self.buffer.pop_front();
+        }
+
+        while self.buffer.len() < self.n {
+            self.buffer.push_back(self.iter.next()?);
+        }
+
+        Some(self.buffer.iter().collect())
+    }
+}
+
+pub struct ReplaceAt<I>
+    where I: Iterator
+{
+    iter: I,
+    index: usize,
+    pos: usize,
+    value: Option<I::Item>
+}
+
+impl<I> Iterator for ReplaceAt<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let x = self.iter.next()?;
+        let pos = self.pos;
+        self.pos += 1;
+
+        if pos == self.index {
+            self.value.take()
+        } else {
+            Some(x)
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct InsertAt<I>
+    where I: Iterator
+{
+    iter: I,
+    index: usize,
+    pos: usize,
+    value: Option<I::Item>
+}
+
+impl<I> Iterator for InsertAt<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.pos == self.index && self.value.is_some() {
+            return self.value.take();
+        }
+
+        match self.iter.next() {
+            Some(x) => {
+                self.pos += 1;
+                Some(x)
+            },
+            None => self.value.take()
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let extra = if self.value.is_some() { 1 } else { 0 };
+        let (lower, upper) = self.iter.size_hint();
+        (lower.saturating_add(extra), upper.and_then(|u| u.checked_add(extra)))
+    }
+}
+
+pub struct RemoveAt<I> {
+    iter: I,
+    index: usize,
+    pos: usize
+}
+
+impl<I> Iterator for RemoveAt<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.pos == self.index {
+            self.pos += 1;
+            self.iter.next()?;
+        }
+
+        self.pos += 1;
+        self.iter.next()
+    }
+}
+
+/// Yields `seed`, then repeatedly applies `step` to the last value yielded until it returns
+/// `None`. This is the "unfold" pattern, and is the same as `std::iter::successors`.
+#[inline]
+pub fn generate<T, F>(seed: T, step: F) -> Generate<T, F>
+    where F: FnMut(&T) -> Option<T>
+{
+    Generate { next: Some(seed), step }
+}
+
+pub struct Generate<T, F> {
+    next: Option<T>,
+    step: F
+}
+
+impl<T, F> Iterator for Generate<T, F>
+    where F: FnMut(&T) -> Option<T>
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        let current = self.next.take()?;
+        self.next = (self.step)(&current);
+        Some(current)
+    }
+}
+
+pub struct Ungroup<I, K, V>
+    where V: IntoIterator
+{
+    iter: I,
+    current: Option<(K, V::IntoIter)>
+}
+
+impl<I, K, V> Iterator for Ungroup<I, K, V>
+    where I: Iterator<Item = (K, V)>,
+          K: Clone,
+          V: IntoIterator
+{
+    type Item = (K, V::Item);
+
+    fn next(&mut self) -> Option<(K, V::Item)> {
+        loop {
+            if let Some((ref key, ref mut items)) = self.current {
+                if let Some(item) = items.next() {
+                    return Some((key.clone(), item));
+                }
+            }
+
+            let (key, items) = self.iter.next()?;
+            self.current = Some((key, items.into_iter()));
+        }
+    }
+}
+
+pub struct SelectManyWith<I, U, CF, RF>
+    where I: Iterator,
+          U: IntoIterator
+{
+    iter: I,
+    collection: CF,
+    result: RF,
+    current: Option<(I::Item, U::IntoIter)>
+}
+
+impl<I, U, R, CF, RF> Iterator for SelectManyWith<I, U, CF, RF>
+    where I: Iterator,
+          I::Item: Clone,
+          U: IntoIterator,
+          CF: FnMut(&I::Item) -> U,
+          RF: FnMut(&I::Item, U::Item) -> R
+{
+    type Item = R;
+
+    fn next(&mut self) -> Option<R> {
+        loop {
+            if let Some((ref outer, ref mut inner)) = self.current {
+                if let Some(item) = inner.next() {
+                    return Some((self.result)(outer, item));
+                }
+            }
+
+            let outer = self.iter.next()?;
+            let inner = (self.collection)(&outer).into_iter();
+            self.current = Some((outer, inner));
+        }
+    }
+}
+
+/// Groups the items by key, preserving the order in which the keys first appear and the
+/// order of the items within each group.
+fn group_in_order<I, K, F>(iter: I, mut key_selector: F) -> Vec<(K, Vec<I::Item>)>
+    where I: Iterator,
+          K: hash::Hash + Eq,
+          F: FnMut(&I::Item) -> K
+{
+    let mut positions = HashMap::new();
+    let mut groups = Vec::new();
+
+    for x in iter {
+        let next_position = groups.len();
+        let position = *positions.entry(key_selector(&x)).or_insert(next_position);
+        if position == next_position {
+            groups.push(Vec::new());
+        }
+        groups[position].push(x);
+    }
+
+    let mut keys = positions.into_iter().collect::<Vec<_>>();
+    keys.sort_by_key(|&(_, position)| position);
+    keys.into_iter().map(|(key, _)| key).zip(groups).collect()
+}
+
+pub struct InterleaveWeighted<A, B> {
+    a: A,
+    b: B,
+    a_weight: usize,
+    b_weight: usize,
+    from_a: bool,
+    drawn: usize
+}
+
+impl<A, B> Iterator for InterleaveWeighted<A, B>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<A::Item> {
+        let weight = if self.from_a { self.a_weight } else { self.b_weight };
+        if self.drawn == weight {
+            self.from_a = !self.from_a;
+            self.drawn = 0;
+        }
+
+        let preferred = if self.from_a { self.a.next() } else { self.b.next() };
+        match preferred {
+            Some(x) => {
+                self.drawn += 1;
+                Some(x)
+            },
+            // One side is exhausted, so the other can be drained without switching back.
+            None => if self.from_a { self.b.next() } else { self.a.next() }
+        }
+    }
+}
+
+pub struct Interleave<A, B> {
+    a: A,
+    b: B,
+    from_a: bool
+}
+
+impl<A, B> Iterator for Interleave<A, B>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<A::Item> {
+        let preferred = if self.from_a { self.a.next() } else { self.b.next() };
+        match preferred {
+            Some(x) => {
+                self.from_a = !self.from_a;
+                Some(x)
+            },
+            None => if self.from_a { self.b.next() } else { self.a.next() }
+        }
+    }
+}
+
+pub struct MergeSorted<A, B>
+    where A: Iterator,
+          B: Iterator
+{
+    a: Peekable<A>,
+    b: Peekable<B>
+}
+
+impl<A, B> Iterator for MergeSorted<A, B>
+    where A: Iterator,
+          A::Item: Ord,
+          B: Iterator<Item = A::Item>
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<A::Item> {
+        let take_b = match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) => b < a,
+            (None, _) => true,
+            (_, None) => false
+        };
+
+        if take_b { self.b.next() } else { self.a.next() }
+    }
+}
+
+pub struct MergeSortedBy<A, B, F>
+    where A: Iterator,
+          B: Iterator
+{
+    a: Peekable<A>,
+    b: Peekable<B>,
+    compare: F
+}
+
+impl<A, B, F> Iterator for MergeSortedBy<A, B, F>
+    where A: Iterator,
+          B: Iterator<Item = A::Item>,
+          F: FnMut(&A::Item, &A::Item) -> Ordering
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<A::Item> {
+        let take_b = match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) => (self.compare)(b, a) == Ordering::Less,
+            (None, _) => true,
+            (_, None) => false
+        };
+
+        if take_b { self.b.next() } else { self.a.next() }
+    }
+}
+
+pub struct IntersperseWith<I, F>
+    where I: Iterator
+{
+    iter: Peekable<I>,
+    sep: F,
+    needs_sep: bool
+}
+
+impl<I, F> Iterator for IntersperseWith<I, F>
+    where I: Iterator,
+          F: FnMut() -> I::Item
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.needs_sep && self.iter.peek().is_some() {
+            self.needs_sep = false;
+            return Some((self.sep)());
+        }
+
+        let x = self.iter.next()?;
+        self.needs_sep = true;
+        Some(x)
+    }
+}
+
+pub struct Intersperse<I>
+    where I: Iterator
+{
+    iter: Peekable<I>,
+    sep: I::Item,
+    needs_sep: bool
+}
+
+impl<I> Iterator for Intersperse<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.needs_sep && self.iter.peek().is_some() {
+            self.needs_sep = false;
+            return Some(self.sep.clone());
+        }
+
+        let x = self.iter.next()?;
+        self.needs_sep = true;
+        Some(x)
+    }
+}
+
+pub struct AggregateSegments<I, B, P, F>
+    where I: Iterator
+{
+    iter: I,
+    is_boundary: P,
+    init: B,
+    f: F,
+    pending: Option<I::Item>
+}
+
+impl<I, B, P, F> Iterator for AggregateSegments<I, B, P, F>
+    where I: Iterator,
+          B: Clone,
+          P: FnMut(&I::Item) -> bool,
+          F: Fn(B, I::Item) -> B
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        let first = match self.pending.take() {
+            Some(x) => x,
+            None => self.iter.next()?
+        };
+
+        let mut acc = (self.f)(self.init.clone(), first);
+        for x in self.iter.by_ref() {
+            if (self.is_boundary)(&x) {
+                self.pending = Some(x);
+                break;
+            }
+            acc = (self.f)(acc, x);
+        }
+
+        Some(acc)
+    }
+}
+
+pub struct PeekableLinq<I>
+    where I: Iterator
+{
+    iter: Peekable<I>
+}
+
+impl<I> PeekableLinq<I>
+    where I: Iterator
+{
+    #[inline]
+    pub fn peek(&mut self) -> Option<&I::Item> {
+        self.iter.peek()
+    }
+
+    /// Collects the leading run of elements that satisfy the predicate. Unlike
+    /// `take_while`, the first element that fails the predicate is not lost: it will be
+    /// returned by the next call to `next`.
+    pub fn peek_while<P>(&mut self, mut predicate: P) -> Vec<I::Item>
+        where P: FnMut(&I::Item) -> bool
+    {
+        let mut result = Vec::new();
+        while let Some(x) = self.iter.next_if(|x| predicate(x)) {
+            result.push(x);
+        }
+        result
+    }
+}
+
+impl<I> Iterator for PeekableLinq<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct GapsBetween<I, P> {
+    iter: I,
+    predicate: P
+}
+
+impl<I, P> Iterator for GapsBetween<I, P>
+    where I: Iterator,
+          P: FnMut(&I::Item) -> bool
+{
+    type Item = (I::Item, usize);
+
+    #[inline]
+    fn next(&mut self) -> Option<(I::Item, usize)> {
+        for (gap, x) in self.iter.by_ref().enumerate() {
+            if (self.predicate)(&x) {
+                return Some((x, gap));
+            }
+        }
+        None
+    }
+}
+
+pub struct DistinctRecent<I>
+    where I: Iterator
+{
+    iter: I,
+    capacity: usize,
+    generation: u64,
+    // Each live element mapped to the generation at which it was last seen.
+    last_seen: HashMap<I::Item, u64>,
+    // Least recently seen at the front. Entries whose generation no longer matches
+    // `last_seen` are stale and are skipped on eviction.
+    order: VecDeque<(I::Item, u64)>
+}
+
+impl<I> DistinctRecent<I>
+    where I: Iterator,
+          I::Item: hash::Hash + Eq
+{
+    fn evict_oldest(&mut self) {
+        while let Some((oldest, generation)) = self.order.pop_front() {
+            if self.last_seen.get(&oldest) == Some(&generation) {
+                self.last_seen.remove(&oldest);
+                return;
+            }
+        }
+    }
+
+    fn compact(&mut self) {
+        let last_seen = &self.last_seen;
+        self.order.retain(|(x, generation)| last_seen.get(x) == Some(generation));
+    }
+}
+
+impl<I> Iterator for DistinctRecent<I>
+    where I: Iterator,
+          I::Item: hash::Hash + Eq + Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        while let Some(x) = self.iter.next() {
+            self.generation += 1;
+            let generation = self.generation;
+
+            if let Some(g) = self.last_seen.get_mut(&x) {
+                // Seeing it again makes it the most recently used.
+                *g = generation;
+                self.order.push_back((x, generation));
+                // Repeats leave stale entries behind; drop them before they outnumber the live ones.
+                if self.order.len() > 2 * self.capacity {
+                    self.compact();
+                }
+                continue;
+            }
+
+            if self.capacity > 0 {
+                if self.last_seen.len() == self.capacity {
+                    self.evict_oldest();
+                }
+                self.last_seen.insert(x.clone(), generation);
+                self.order.push_back((x.clone(), generation));
+            }
+
+            return Some(x);
+        }
+
+        None
+    }
+}
+
+/// A bottom-up merge sort: start with runs of length one and repeatedly merge adjacent
+/// pairs of runs until only one is left.
+fn merge_sort<K: Ord, T>(items: Vec<(K, T)>) -> Vec<(K, T)> {
+    let mut runs = items.into_iter().map(|x| vec![x]).collect::<Vec<_>>();
+
+    while runs.len() > 1 {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(2));
+        let mut pairs = runs.into_iter();
+        while let Some(left) = pairs.next() {
+            match pairs.next() {
+                Some(right) => merged.push(merge_runs(left, right)),
+                None => merged.push(left)
+            }
+        }
+        runs = merged;
+    }
+
+    runs.pop().unwrap_or_default()
+}
+
+fn merge_runs<K: Ord, T>(left: Vec<(K, T)>, right: Vec<(K, T)>) -> Vec<(K, T)> {
+    let mut result = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+
+    loop {
+        // Only take from the right when it is strictly smaller; this is what makes it stable.
+        let take_right = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => r.0 < l.0,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => return result
+        };
+
+        let next = if take_right { right.next() } else { left.next() };
+        result.extend(next);
+    }
+}
+
+pub struct OrderByMerge<T> {
+    items: vec::IntoIter<T>
+}
+
+impl<T> Iterator for OrderByMerge<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.items.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+/// The result of a three-way partition, in (less, equal, greater) order.
+pub type ThreeWayPartition<T> = (Vec<T>, Vec<T>, Vec<T>);
+
+pub struct WindowPadded<I, F>
+    where I: Iterator
+{
+    iter: I,
+    before: usize,
+    after: usize,
+    f: F,
+    buffer: VecDeque<I::Item>,
+    // The position in `buffer` of the element whose window we will compute next.
+    current: usize,
+    exhausted: bool
+}
+
+impl<I, F, R> Iterator for WindowPadded<I, F>
+    where I: Iterator,
+          F: FnMut(&[I::Item]) -> R
+{
+    type Item = R;
+
+    fn next(&mut self) -> Option<R> {
+        while !self.exhausted && self.buffer.len() <= self.current + self.after {
+            match self.iter.next() {
+                Some(x) => self.buffer.push_back(x),
+                None => self.exhausted = true
+            }
+        }
+
+        if self.current >= self.buffer.len() {
+            return None;
+        }
+
+        let end = cmp::min(self.buffer.len(), self.current + self.after + 1);
+        let result = (self.f)(&self.buffer.make_contiguous()[..end]);
+
+        if self.current == self.before {
+            self.buffer.pop_front();
+        } else {
+            self.current += 1;
+        }
+
+        Some(result)
+    }
+}
+
+pub struct MapStateful<I, S, F> {
+    iter: I,
+    state: S,
+    f: F
+}
+
+impl<I, S, F, R> Iterator for MapStateful<I, S, F>
+    where I: Iterator,
+          F: FnMut(&mut S, I::Item) -> R
+{
+    type Item = R;
+
+    #[inline]
+    fn next(&mut self) -> Option<R> {
+        let x = self.iter.next()?;
+        Some((self.f)(&mut self.state, x))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// A key and the elements that have that key, as returned by `group_by`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grouping<K, V> {
+    pub key: K,
+    pub elements: Vec<V>
+}
+
+impl<K, V> IntoIterator for Grouping<K, V> {
+    type Item = V;
+    type IntoIter = vec::IntoIter<V>;
+
+    fn into_iter(self) -> vec::IntoIter<V> {
+        self.elements.into_iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a Grouping<K, V> {
+    type Item = &'a V;
+    type IntoIter = slice::Iter<'a, V>;
+
+    fn into_iter(self) -> slice::Iter<'a, V> {
+        self.elements.iter()
+    }
+}
+
+/// A multimap, as returned by `to_lookup`. Like C#'s `ILookup`, looking up a key that is
+/// not present gives you an empty slice rather than an error.
+#[derive(Debug, Clone)]
+pub struct Lookup<K, V>
+    where K: hash::Hash + Eq
+{
+    groups: HashMap<K, Vec<V>>
+}
+
+impl<K, V> Lookup<K, V>
+    where K: hash::Hash + Eq
+{
+    pub fn get(&self, key: &K) -> &[V] {
+        self.groups.get(key).map_or(&[], |v| v.as_slice())
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.groups.contains_key(key)
+    }
+
+    /// The number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Iterates over each key and its elements, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &[V])> {
+        self.groups.iter().map(|(k, v)| (k, v.as_slice()))
+    }
+}
+
+/// A set that preserves insertion order, as returned by `to_ordered_set`.
+#[derive(Debug, Clone)]
+pub struct OrderedSet<T>
+    where T: hash::Hash + Eq
+{
+    items: Vec<T>,
+    set: HashSet<T>
+}
+
+impl<T> OrderedSet<T>
+    where T: hash::Hash + Eq
+{
+    pub fn contains(&self, value: &T) -> bool {
+        self.set.contains(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The elements in the order they were first seen.
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Iterates over the elements in the order they were first seen.
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.items.iter()
+    }
+}
+
+impl<T> IntoIterator for OrderedSet<T>
+    where T: hash::Hash + Eq
+{
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+pub struct Join<I, K, TInner, FO, FR>
+    where I: Iterator,
+          K: hash::Hash + Eq
+{
+    outer: I,
+    lookup: Lookup<K, TInner>,
+    outer_key: FO,
+    result: FR,
+    // The outer element we are currently matching, and its key.
+    current: Option<(I::Item, K)>,
+    // The index of the next inner element to match against `current`.
+    index: usize
+}
+
+impl<I, K, TInner, FO, FR, TResult> Iterator for Join<I, K, TInner, FO, FR>
+    where I: Iterator,
+          K: hash::Hash + Eq,
+          FO: FnMut(&I::Item) -> K,
+          FR: FnMut(&I::Item, &TInner) -> TResult
+{
+    type Item = TResult;
+
+    fn next(&mut self) -> Option<TResult> {
+        loop {
+            if let Some((ref outer, ref key)) = self.current {
+                if let Some(inner) = self.lookup.get(key).get(self.index) {
+                    self.index += 1;
+                    return Some((self.result)(outer, inner));
+                }
+            }
+
+            let outer = self.outer.next()?;
+            let key = (self.outer_key)(&outer);
+            self.current = Some((outer, key));
+            self.index = 0;
+        }
+    }
+}
+
+pub struct GroupJoin<I, K, TInner, FO, FR>
+    where K: hash::Hash + Eq
+{
+    outer: I,
+    lookup: Lookup<K, TInner>,
+    outer_key: FO,
+    result: FR
+}
+
+impl<I, K, TInner, FO, FR, TResult> Iterator for GroupJoin<I, K, TInner, FO, FR>
+    where I: Iterator,
+          K: hash::Hash + Eq,
+          FO: FnMut(&I::Item) -> K,
+          FR: FnMut(I::Item, &[TInner]) -> TResult
+{
+    type Item = TResult;
+
+    #[inline]
+    fn next(&mut self) -> Option<TResult> {
+        let outer = self.outer.next()?;
+        let key = (self.outer_key)(&outer);
+        Some((self.result)(outer, self.lookup.get(&key)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.outer.size_hint()
+    }
+}
+
+pub struct OrderedEnumerable<T> {
+    items: Vec<T>,
+    comparators: Vec<BoxedComparer<T>>,
+    // Becomes `Some` once the items have been sorted.
+    sorted: Option<vec::IntoIter<T>>
+}
+
+impl<T> OrderedEnumerable<T> {
+    fn new(items: Vec<T>, comparator: BoxedComparer<T>) -> Self {
+        OrderedEnumerable { items, comparators: vec![comparator], sorted: None }
+    }
+
+    /// The equivalent of C#'s `ThenBy`: sorts elements that are equal by all the previous
+    /// keys by this one.
+    pub fn then_by<K, F>(self, key: F) -> OrderedEnumerable<T>
+        where K: Ord,
+              F: Fn(&T) -> K + 'static
+    {
+        self.add_comparator(Box::new(move |a, b| key(a).cmp(&key(b))))
+    }
+
+    /// The equivalent of C#'s `ThenByDescending`.
+    pub fn then_by_descending<K, F>(self, key: F) -> OrderedEnumerable<T>
+        where K: Ord,
+              F: Fn(&T) -> K + 'static
+    {
+        self.add_comparator(Box::new(move |a, b| key(b).cmp(&key(a))))
+    }
+
+    fn add_comparator(mut self, comparator: BoxedComparer<T>) -> Self {
+        // If iteration has already started, put what's left back so it gets sorted again.
+        if let Some(remaining) = self.sorted.take() {
+            self.items = remaining.collect();
+        }
+        self.comparators.push(comparator);
+        self
+    }
+
+    fn sort(&mut self) -> vec::IntoIter<T> {
+        let comparators = &self.comparators;
+        let mut items = mem::take(&mut self.items);
+        items.sort_by(|a, b| {
+            comparators.iter()
+                .map(|cmp| cmp(a, b))
+                .find(|&ord| ord != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        items.into_iter()
+    }
+}
+
+impl<T> Iterator for OrderedEnumerable<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.sorted.is_none() {
+            self.sorted = Some(self.sort());
+        }
+        self.sorted.as_mut().and_then(|s| s.next())
+    }
+}
+
+/// The error returned by `to_dictionary` when two elements have the same key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKeyError<K> {
+    pub key: K
+}
+
+impl<K: fmt::Debug> fmt::Display for DuplicateKeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an element with the key {:?} has already been added", self.key)
+    }
+}
+
+impl<K: fmt::Debug> error::Error for DuplicateKeyError<K> { }
+
+pub struct DefaultIfEmpty<I>
+    where I: Iterator
+{
+    iter: I,
+    // We can only find out whether the sequence is empty by asking for the first element,
+    // so this is `Some` until the first call to `next`.
+    default: Option<I::Item>
+}
+
+impl<I> Iterator for DefaultIfEmpty<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        match self.default.take() {
+            None => self.iter.next(),
+            Some(default) => Some(self.iter.next().unwrap_or(default))
+        }
+    }
+}
+
+pub struct Reverse<I>
+    where I: Iterator
+{
+    // Drained into `items` on the first call to `next`.
+    iter: Option<I>,
+    items: Vec<I::Item>
+}
+
+impl<I> Iterator for Reverse<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if let Some(iter) = self.iter.take() {
+            self.items = iter.collect();
+        }
+        self.items.pop()
+    }
+}
+
+pub struct ZipWith<A, B, F> {
+    a: A,
+    b: B,
+    f: F
+}
+
+impl<A, B, F, R> Iterator for ZipWith<A, B, F>
+    where A: Iterator,
+          B: Iterator,
+          F: FnMut(A::Item, B::Item) -> R
+{
+    type Item = R;
+
+    #[inline]
+    fn next(&mut self) -> Option<R> {
+        let a = self.a.next()?;
+        let b = self.b.next()?;
+        Some((self.f)(a, b))
+    }
+}
+
+pub struct Zip3<A, B, C> {
+    a: A,
+    b: B,
+    c: C
+}
+
+impl<A, B, C> Iterator for Zip3<A, B, C>
+    where A: Iterator,
+          B: Iterator,
+          C: Iterator
+{
+    type Item = (A::Item, B::Item, C::Item);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.a.next()?, self.b.next()?, self.c.next()?))
+    }
+}
+
+pub struct Chunk<I> {
+    iter: I,
+    size: usize
+}
+
+impl<I> Iterator for Chunk<I>
+    where I: Iterator
+{
+    type Item = Vec<I::Item>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        let chunk = self.iter.by_ref().take(self.size).collect::<Vec<_>>();
+        if chunk.is_empty() { None } else { Some(chunk) }
+    }
+}
+
+pub struct Windows<I>
+    where I: Iterator
+{
+    iter: I,
+    size: usize,
+    buffer: VecDeque<I::Item>
+}
+
+impl<I> Iterator for Windows<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        if self.buffer.len() == self.size {
+            self.buffer.pop_front();
+        }
+
+        while self.buffer.len() < self.size {
+            self.buffer.push_back(self.iter.next()?);
+        }
+
+        Some(self.buffer.iter().cloned().collect())
+    }
+}
+
+pub struct Scan<I, A, F> {
+    iter: I,
+    acc: A,
+    f: F
+}
+
+impl<I, A, F> Iterator for Scan<I, A, F>
+    where I: Iterator,
+          A: Clone,
+          F: FnMut(&A, I::Item) -> A
+{
+    type Item = A;
+
+    #[inline]
+    fn next(&mut self) -> Option<A> {
+        let x = self.iter.next()?;
+        self.acc = (self.f)(&self.acc, x);
+        Some(self.acc.clone())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct ScanTry<I, A, F> {
+    iter: I,
+    // Becomes `None` once `f` has failed.
+    acc: Option<A>,
+    f: F
+}
+
+impl<I, A, E, F> Iterator for ScanTry<I, A, F>
+    where I: Iterator,
+          A: Clone,
+          F: FnMut(&A, I::Item) -> Result<A, E>
+{
+    type Item = Result<A, E>;
+
+    fn next(&mut self) -> Option<Result<A, E>> {
+        let acc = self.acc.as_ref()?;
+        let x = self.iter.next()?;
+        match (self.f)(acc, x) {
+            Ok(a) => {
+                self.acc = Some(a.clone());
+                Some(Ok(a))
+            },
+            Err(e) => {
+                self.acc = None;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+pub struct CumulativeSum<I>
+    where I: Iterator
+{
+    iter: I,
+    total: Option<I::Item>
+}
+
+impl<I> Iterator for CumulativeSum<I>
+    where I: Iterator,
+          I::Item: Copy + Add<Output = I::Item>
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let x = self.iter.next()?;
+        let total = match self.total {
+            Some(t) => t + x,
+            None => x
+        };
+        self.total = Some(total);
+        Some(total)
+    }
+}
+
+pub struct CumulativeMax<I>
+    where I: Iterator
+{
+    iter: I,
+    max: Option<I::Item>
+}
+
+impl<I> Iterator for CumulativeMax<I>
+    where I: Iterator,
+          I::Item: Ord + Clone
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let x = self.iter.next()?;
+        let max = match self.max.take() {
+            Some(m) if m >= x => m,
+            _ => x
+        };
+        self.max = Some(max.clone());
+        Some(max)
+    }
+}
+
+/// Which part of the result of `partition3` an element belongs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition3 {
+    Left,
+    Middle,
+    Right
+}
+
+pub struct InRange<I> {
+    iter: I,
+    pos: usize,
+    start: usize,
+    // Exclusive, `None` if the range has no end.
+    end: Option<usize>
+}
+
+impl<I> Iterator for InRange<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        while self.pos < self.start {
+            self.iter.next()?;
+            self.pos += 1;
+        }
+
+        if self.end.is_some_and(|end| self.pos >= end) {
+            return None;
+        }
+
+        self.pos += 1;
+        self.iter.next()
+    }
+}
+
+pub struct TakeLast<I>
+    where I: Iterator
+{
+    // Drained into `buffer` on the first call to `next`.
+    iter: Option<I>,
+    n: usize,
+    buffer: VecDeque<I::Item>
+}
+
+impl<I> Iterator for TakeLast<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if let Some(iter) = self.iter.take() {
+            if self.n > 0 {
+                for x in iter {
+                    if self.buffer.len() == self.n {
+                        self.buffer.pop_front();
+                    }
+                    self.buffer.push_back(x);
+                }
+            }
+        }
+
+        self.buffer.pop_front()
+    }
+}
+
+pub struct SkipLast<I>
+    where I: Iterator
+{
+    iter: I,
+    n: usize,
+    buffer: VecDeque<I::Item>
+}
+
+impl<I> Iterator for SkipLast<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        while self.buffer.len() <= self.n {
+            self.buffer.push_back(self.iter.next()?);
+        }
+        self.buffer.pop_front()
+    }
+}
+
+pub struct OfVariant<I, F> {
+    iter: I,
+    extract: F
+}
+
+impl<I, F, T> Iterator for OfVariant<I, F>
+    where I: Iterator,
+          F: FnMut(I::Item) -> Option<T>
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        for x in self.iter.by_ref() {
+            if let Some(t) = (self.extract)(x) {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+pub struct DistinctUntilChanged<I>
+    where I: Iterator
+{
+    iter: I,
+    last: Option<I::Item>
+}
+
+impl<I> Iterator for DistinctUntilChanged<I>
+    where I: Iterator,
+          I::Item: PartialEq + Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        for x in self.iter.by_ref() {
+            if self.last.as_ref() != Some(&x) {
+                self.last = Some(x.clone());
+                return Some(x);
+            }
+        }
+        None
+    }
+}
+
+pub struct DistinctUntilChangedBy<I, F, K> {
+    iter: I,
+    key: F,
+    last_key: Option<K>
+}
+
+impl<I, F, K> Iterator for DistinctUntilChangedBy<I, F, K>
+    where I: Iterator,
+          K: PartialEq,
+          F: FnMut(&I::Item) -> K
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        for x in self.iter.by_ref() {
+            let k = (self.key)(&x);
+            if self.last_key.as_ref() != Some(&k) {
+                self.last_key = Some(k);
+                return Some(x);
+            }
+        }
+        None
+    }
+}
+
+pub struct SelectMany<I, F, J> {
+    iter: I,
+    f: F,
+    current: Option<J>
+}
+
+impl<I, F, U> Iterator for SelectMany<I, F, U::IntoIter>
+    where I: Iterator,
+          F: FnMut(I::Item) -> U,
+          U: IntoIterator
+{
+    type Item = U::Item;
+
+    fn next(&mut self) -> Option<U::Item> {
+        loop {
+            if let Some(ref mut inner) = self.current {
+                if let Some(item) = inner.next() {
+                    return Some(item);
+                }
+            }
+
+            let outer = self.iter.next()?;
+            self.current = Some((self.f)(outer).into_iter());
+        }
+    }
+}
+
+pub struct SelectManyIndexed<I, F, J> {
+    iter: I,
+    f: F,
+    index: usize,
+    current: Option<J>
+}
+
+impl<I, F, U> Iterator for SelectManyIndexed<I, F, U::IntoIter>
+    where I: Iterator,
+          F: FnMut(usize, I::Item) -> U,
+          U: IntoIterator
+{
+    type Item = U::Item;
+
+    fn next(&mut self) -> Option<U::Item> {
+        loop {
+            if let Some(ref mut inner) = self.current {
+                if let Some(item) = inner.next() {
+                    return Some(item);
+                }
+            }
+
+            let outer = self.iter.next()?;
+            self.current = Some((self.f)(self.index, outer).into_iter());
+            self.index += 1;
+        }
+    }
+}
+
+pub struct FlattenLinq<I>
+    where I: Iterator,
+          I::Item: IntoIterator
+{
+    iter: I,
+    current: Option<<I::Item as IntoIterator>::IntoIter>
+}
+
+impl<I> Iterator for FlattenLinq<I>
+    where I: Iterator,
+          I::Item: IntoIterator
+{
+    type Item = <I::Item as IntoIterator>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(ref mut inner) = self.current {
+                if let Some(item) = inner.next() {
+                    return Some(item);
+                }
+            }
+
+            self.current = Some(self.iter.next()?.into_iter());
+        }
+    }
+}
+
+struct TeeBuffer<I>
+    where I: Iterator
+{
+    iter: I,
+    // Elements read from `iter` that only the fork identified by `owner` has still to see.
+    items: VecDeque<I::Item>,
+    owner: bool
+}
+
+pub struct Tee<I>
+    where I: Iterator
+{
+    buffer: Rc<RefCell<TeeBuffer<I>>>,
+    id: bool
+}
+
+impl<I> Iterator for Tee<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let mut buffer = self.buffer.borrow_mut();
+        if buffer.owner == self.id {
+            if let Some(x) = buffer.items.pop_front() {
+                return Some(x);
+            }
+        }
+
+        // We are ahead of (or level with) the other fork, so read a new element and
+        // leave a copy behind for it.
+        let x = buffer.iter.next()?;
+        buffer.owner = !self.id;
+        buffer.items.push_back(x.clone());
+        Some(x)
+    }
+}
+
+struct Cache<I>
+    where I: Iterator
+{
+    iter: I,
+    items: Vec<I::Item>
+}
+
+pub struct Cached<I>
+    where I: Iterator
+{
+    cache: Rc<RefCell<Cache<I>>>,
+    index: usize
+}
+
+// Not derived because a clone starts again from the beginning, and `I` itself need not be
+// `Clone`.
+impl<I> Clone for Cached<I>
+    where I: Iterator
+{
+    fn clone(&self) -> Self {
+        Cached { cache: self.cache.clone(), index: 0 }
+    }
+}
+
+impl<I> Iterator for Cached<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let mut cache = self.cache.borrow_mut();
+        if self.index == cache.items.len() {
+            let x = cache.iter.next()?;
+            cache.items.push(x);
+        }
+
+        self.index += 1;
+        Some(cache.items[self.index - 1].clone())
+    }
+}
+
+pub struct CartesianProduct<A, B>
+    where A: Iterator,
+          B: Iterator
+{
+    a: A,
+    // Drained into `items` on the first call to `next`.
+    b: Option<B>,
+    items: Vec<B::Item>,
+    // The current element of `a` and the index of the next item to pair it with.
+    current: Option<(A::Item, usize)>
+}
+
+impl<A, B> Iterator for CartesianProduct<A, B>
+    where A: Iterator,
+          A::Item: Clone,
+          B: Iterator,
+          B::Item: Clone
+{
+    type Item = (A::Item, B::Item);
+
+    fn next(&mut self) -> Option<(A::Item, B::Item)> {
+        if let Some(b) = self.b.take() {
+            self.items.extend(b);
+        }
+
+        if self.items.is_empty() {
+            return None;
+        }
+
+        loop {
+            if let Some((ref x, ref mut index)) = self.current {
+                if let Some(y) = self.items.get(*index) {
+                    *index += 1;
+                    return Some((x.clone(), y.clone()));
+                }
+            }
+
+            self.current = Some((self.a.next()?, 0));
+        }
+    }
+}
+
+/// Counts the occurrences of each distinct item, preserving the order in which the items
+/// first appear.
+fn counts_in_order<I>(iter: I) -> Vec<(I::Item, usize)>
+    where I: Iterator,
+          I::Item: hash::Hash + Eq + Clone
+{
+    let mut positions: HashMap<I::Item, usize> = HashMap::new();
+    let mut counts: Vec<(I::Item, usize)> = Vec::new();
+
+    for x in iter {
+        match positions.entry(x.clone()) {
+            Entry::Occupied(e) => counts[*e.get()].1 += 1,
+            Entry::Vacant(e) => {
+                e.insert(counts.len());
+                counts.push((x, 1));
+            }
+        }
+    }
+
+    counts
+}
+
+pub struct Shuffle<T> {
+    items: vec::IntoIter<T>
+}
+
+impl<T> Iterator for Shuffle<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.items.next()
+    }
+}
+
+pub struct Lag<I>
+    where I: Iterator
+{
+    iter: I,
+    n: usize,
+    fill: I::Item,
+    // The last `n` elements yielded, plus the current one.
+    buffer: VecDeque<I::Item>
+}
+
+impl<I> Iterator for Lag<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<(I::Item, I::Item)> {
+        let x = self.iter.next()?;
+        self.buffer.push_back(x.clone());
+        let previous = if self.buffer.len() > self.n {
+            self.buffer.pop_front().unwrap()
+        } else {
+            self.fill.clone()
+        };
+        Some((x, previous))
+    }
+}
+
+pub struct Lead<I>
+    where I: Iterator
+{
+    iter: I,
+    n: usize,
+    fill: I::Item,
+    // The current element followed by up to `n` elements read ahead of it.
+    buffer: VecDeque<I::Item>
+}
+
+impl<I> Iterator for Lead<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<(I::Item, I::Item)> {
+        while self.buffer.len() <= self.n {
+            match self.iter.next() {
+                Some(x) => self.buffer.push_back(x),
+                None => break
+            }
+        }
+
+        let x = self.buffer.pop_front()?;
+        let following = if self.n == 0 {
+            x.clone()
+        } else {
+            self.buffer.get(self.n - 1).cloned().unwrap_or_else(|| self.fill.clone())
+        };
+        Some((x, following))
+    }
+}
+
+pub struct TakeEvery<I> {
+    iter: I,
+    step: usize,
+    // How many elements to skip before the next one to yield.
+    skip: usize
+}
+
+impl<I> Iterator for TakeEvery<I>
+    where I: Iterator
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let x = self.iter.nth(self.skip)?;
+        self.skip = self.step - 1;
+        Some(x)
+    }
+}
+
+/// Conversion to `f64` for the numbers that `average` accepts. Unlike `Into<f64>` this is
+/// implemented for `i64`, `u64`, `isize` and `usize`, which may round to the nearest
+/// representable `f64` when their magnitude exceeds 2^53.
+pub trait ToF64 {
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_to_f64 {
+    ($($t:ty),*) => {
+        $(
+            impl ToF64 for $t {
+                #[inline]
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    }
+}
+
+impl_to_f64!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+/// The equivalent of C#'s `IComparer<T>`: a way of ordering values that need not be `Ord`,
+/// or that should be ordered differently from their `Ord` implementation. Any closure
+/// taking two references and returning an `Ordering` is a `Comparer`.
+pub trait Comparer<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+impl<T, F> Comparer<T> for F
+    where F: Fn(&T, &T) -> Ordering
+{
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// A `Comparer` for strings that ignores case. See `case_insensitive`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaseInsensitive;
+
+impl<S> Comparer<S> for CaseInsensitive
+    where S: AsRef<str>
+{
+    fn compare(&self, a: &S, b: &S) -> Ordering {
+        let a = a.as_ref().chars().flat_map(char::to_lowercase);
+        let b = b.as_ref().chars().flat_map(char::to_lowercase);
+        a.cmp(b)
+    }
+}
+
+/// Returns a `Comparer` that orders strings ignoring case, like C#'s
+/// `StringComparer.OrdinalIgnoreCase`.
+#[inline]
+pub fn case_insensitive() -> CaseInsensitive {
+    CaseInsensitive
+}
+
+/// The equivalent of C#'s `IEqualityComparer<T>`: a way of deciding which values are equal
+/// that differs from their `Eq` implementation, or for values that don't have one. Values
+/// that are equal must have the same hash.
+pub trait EqualityComparer<T> {
+    fn equals(&self, a: &T, b: &T) -> bool;
+    fn hash(&self, value: &T) -> u64;
+}
+
+impl<S> EqualityComparer<S> for CaseInsensitive
+    where S: AsRef<str>
+{
+    fn equals(&self, a: &S, b: &S) -> bool {
+        self.compare(a, b) == Ordering::Equal
+    }
+
+    fn hash(&self, value: &S) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for c in value.as_ref().chars().flat_map(char::to_lowercase) {
+            hash::Hash::hash(&c, &mut hasher);
+        }
+        hash::Hasher::finish(&hasher)
+    }
+}
+
+/// A set whose notion of equality comes from an `EqualityComparer`. Items are bucketed by
+/// the comparer's hash, and the comparer's `equals` then decides within a bucket.
+struct ComparerSet<T, C> {
+    comparer: C,
+    buckets: HashMap<u64, Vec<T>>
 }
 
+impl<T, C> ComparerSet<T, C>
+    where C: EqualityComparer<T>
+{
+    fn new(comparer: C) -> Self {
+        ComparerSet { comparer, buckets: HashMap::new() }
+    }
+
+    /// Adds `value` to the set, returning false if an equal value was already present.
+    fn insert(&mut self, value: T) -> bool {
+        let comparer = &self.comparer;
+        let bucket = self.buckets.entry(comparer.hash(&value)).or_default();
+        if bucket.iter().any(|x| comparer.equals(x, &value)) {
+            false
+        } else {
+            bucket.push(value);
+            true
+        }
+    }
+
+    /// Removes the value equal to `value` from the set, returning false if there wasn't one.
+    fn remove(&mut self, value: &T) -> bool {
+        let comparer = &self.comparer;
+        match self.buckets.get_mut(&comparer.hash(value)) {
+            Some(bucket) => match bucket.iter().position(|x| comparer.equals(x, value)) {
+                Some(i) => {
+                    bucket.swap_remove(i);
+                    true
+                },
+                None => false
+            },
+            None => false
+        }
+    }
+}
+
+/// A buffered copy of a sequence, as returned by `to_snapshot`. `as_slice` and `len`
+/// reflect the elements that have not yet been iterated over.
+#[derive(Debug, Clone)]
+pub struct Snapshot<T> {
+    items: vec::IntoIter<T>
+}
+
+impl<T> Snapshot<T> {
+    pub fn as_slice(&self) -> &[T] {
+        self.items.as_slice()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.len() == 0
+    }
+}
+
+impl<T> Iterator for Snapshot<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.items.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+pub struct Deltas<I>
+    where I: Iterator
+{
+    iter: I,
+    previous: Option<I::Item>
+}
+
+impl<I> Iterator for Deltas<I>
+    where I: Iterator,
+          I::Item: Copy + Sub<Output = I::Item>
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let previous = match self.previous {
+            Some(p) => p,
+            None => self.iter.next()?
+        };
+
+        let x = self.iter.next()?;
+        self.previous = Some(x);
+        Some(x - previous)
+    }
+}
+
+/// An element in the heap used by `top_n_by_key`. Entries are ordered by key, and then so
+/// that the entry that came first is the greater, which keeps the selection stable.
+struct TopNEntry<K, T> {
+    key: K,
+    index: usize,
+    item: T
+}
+
+impl<K: Ord, T> Ord for TopNEntry<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key).then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl<K: Ord, T> PartialOrd for TopNEntry<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> PartialEq for TopNEntry<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, T> Eq for TopNEntry<K, T> {}
+
+/// The `n` items with the largest keys, largest first, holding at most `n` items at a time.
+fn top_n_by_key<I, K, F>(iter: I, n: usize, mut key: F) -> Vec<I::Item>
+    where I: Iterator,
+          K: Ord,
+          F: FnMut(&I::Item) -> K
+{
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    for (index, item) in iter.enumerate() {
+        heap.push(cmp::Reverse(TopNEntry { key: key(&item), index, item }));
+        if heap.len() > n {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec().into_iter().map(|r| r.0.item).collect()
+}
+
+pub struct EnumerateFrom<I> {
+    iter: I,
+    index: usize
+}
+
+impl<I> Iterator for EnumerateFrom<I>
+    where I: Iterator
+{
+    type Item = (usize, I::Item);
+
+    #[inline]
+    fn next(&mut self) -> Option<(usize, I::Item)> {
+        let x = self.iter.next()?;
+        let i = self.index;
+        self.index += 1;
+        Some((i, x))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct SkipUntil<I, P> {
+    iter: I,
+    predicate: P,
+    done: bool
+}
+
+impl<I, P> Iterator for SkipUntil<I, P>
+    where I: Iterator,
+          P: FnMut(&I::Item) -> bool
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.done {
+            return self.iter.next();
+        }
+
+        let x = self.iter.find(&mut self.predicate)?;
+        self.done = true;
+        Some(x)
+    }
+}
+
+pub struct TakeUntil<I, P> {
+    iter: I,
+    predicate: P,
+    done: bool
+}
+
+impl<I, P> Iterator for TakeUntil<I, P>
+    where I: Iterator,
+          P: FnMut(&I::Item) -> bool
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.done {
+            return None;
+        }
+
+        let x = self.iter.next()?;
+        self.done = (self.predicate)(&x);
+        Some(x)
+    }
+}
+
+pub struct RepeatSequence<I>
+    where I: Iterator
+{
+    // The source, until the first pass over it is complete.
+    iter: Option<I>,
+    items: Vec<I::Item>,
+    // The number of times `items` still has to be replayed.
+    replays: usize,
+    index: usize
+}
+
+impl<I> Iterator for RepeatSequence<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if let Some(iter) = self.iter.as_mut() {
+            match iter.next() {
+                Some(x) => {
+                    self.items.push(x.clone());
+                    return Some(x);
+                },
+                None => self.iter = None
+            }
+        }
+
+        if self.items.is_empty() {
+            return None;
+        }
+
+        if self.index == 0 {
+            if self.replays == 0 {
+                return None;
+            }
+            self.replays -= 1;
+        }
+
+        let x = self.items[self.index].clone();
+        self.index = (self.index + 1) % self.items.len();
+        Some(x)
+    }
+}
+
+pub struct Coalesce<I, T> {
+    iter: I,
+    fallback: T
+}
+
+impl<I, T> Iterator for Coalesce<I, T>
+    where I: Iterator<Item = Option<T>>,
+          T: Clone
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        let x = self.iter.next()?;
+        Some(x.unwrap_or_else(|| self.fallback.clone()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct EveryNthReduce<I, F>
+    where I: Iterator
+{
+    iter: I,
+    n: usize,
+    f: F,
+    buffer: Vec<I::Item>
+}
+
+impl<I, F, A> Iterator for EveryNthReduce<I, F>
+    where I: Iterator,
+          F: FnMut(&[I::Item]) -> A
+{
+    type Item = A;
+
+    fn next(&mut self) -> Option<A> {
+        self.buffer.clear();
+        self.buffer.extend(self.iter.by_ref().take(self.n));
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some((self.f)(&self.buffer))
+        }
+    }
+}
+
+pub struct PadRight<I>
+    where I: Iterator
+{
+    iter: I,
+    min_len: usize,
+    fill: I::Item,
+    count: usize
+}
+
+impl<I> Iterator for PadRight<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let x = match self.iter.next() {
+            Some(x) => x,
+            None if self.count < self.min_len => self.fill.clone(),
+            None => return None
+        };
+        self.count += 1;
+        Some(x)
+    }
+}
+
+pub struct PadLeft<I>
+    where I: Iterator
+{
+    // Drained into `items` on the first call to `next`.
+    iter: Option<I>,
+    min_len: usize,
+    fill: I::Item,
+    // The number of copies of `fill` still to yield.
+    padding: usize,
+    items: vec::IntoIter<I::Item>
+}
+
+impl<I> Iterator for PadLeft<I>
+    where I: Iterator,
+          I::Item: Clone
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if let Some(iter) = self.iter.take() {
+            self.items = iter.collect::<Vec<_>>().into_iter();
+            self.padding = self.min_len.saturating_sub(self.items.len());
+        }
+
+        if self.padding > 0 {
+            self.padding -= 1;
+            Some(self.fill.clone())
+        } else {
+            self.items.next()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{case_insensitive, generate, BoxedComparer, DuplicateKeyError, LinqIteratorExtensions, Partition3, SingleError};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::cmp;
+    use std::iter::{empty, once};
+    #[cfg(feature = "serde")]
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn single_for_empty_sequence_returns_none() {
+        let actual = empty::<i32>().single();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn single_for_singleton_sequence_returns_none() {
+        let actual = once(12).single();
+        assert_eq!(actual, Some(12));
+    }
+
+    #[test]
+    fn single_for_sequence_of_length_two_or_more_returns_none() {
+        let actual = (0..2).single();
+        assert_eq!(actual, None);
+
+        let actual = (0..3).single();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn single_or_for_empty_sequence_returns_default() {
+        let actual = empty::<i32>().single_or(42);
+        assert_eq!(actual, 42);
+    }
+
+    #[test]
+    fn single_or_for_singleton_sequence_returns_value_from_sequence() {
+        let actual = once(12).single_or(42);
+        assert_eq!(actual, 12);
+    }
+
+    #[test]
+    fn single_or_for_sequence_of_length_two_or_more_returns_default() {
+        let actual = (0..2).single_or(42);
+        assert_eq!(actual, 42);
+
+        let actual = (0..3).single_or(42);
+        assert_eq!(actual, 42);
+    }
+
+    fn default_generator() -> i32 {
+        130
+    }
+
+    #[test]
+    fn single_or_else_for_empty_sequence_returns_default() {
+        let actual = empty::<i32>().single_or_else(default_generator);
+        assert_eq!(actual, 130);
+    }
+
+    #[test]
+    fn single_or_else_for_singleton_sequence_returns_value_from_sequence() {
+        let actual = once(12).single_or_else(default_generator);
+        assert_eq!(actual, 12);
+    }
+
+    #[test]
+    fn single_or_else_for_sequence_of_length_two_or_more_returns_default() {
+        let actual = (0..2).single_or_else(default_generator);
+        assert_eq!(actual, 130);
+
+        let actual = (0..3).single_or_else(default_generator);
+        assert_eq!(actual, 130);
+    }
+
+    #[test]
+    fn single_or_default_for_empty_sequence_returns_default() {
+        let actual = empty::<i32>().single_or_default();
+        assert_eq!(actual, 0);
+    }
+
+    #[test]
+    fn single_or_default_for_singleton_sequence_returns_value_from_sequence() {
+        let actual = once(12).single_or_default();
+        assert_eq!(actual, 12);
+    }
+
+    #[test]
+    fn single_or_default_for_sequence_of_length_two_or_more_returns_default() {
+        let actual = (0..2).single_or_default();
+        assert_eq!(actual, 0);
+
+        let actual = (0..3).single_or_default();
+        assert_eq!(actual, 0);
+    }
+
+    #[test]
+    fn first_or_for_empty_sequence_returns_default() {
+        assert_eq!(empty::<i32>().first_or(42), 42);
+    }
+
+    #[test]
+    fn first_or_for_singleton_sequence_returns_first_value_from_sequence() {
+        assert_eq!(once(12).first_or(42), 12);
+    }
+
+    #[test]
+    fn first_or_for_sequence_of_length_two_or_more_returns_first_value_from_sequence() {
+        let actual = (1..3).first_or(42);
+        assert_eq!(actual, 1);
+
+        let actual = (1..4).first_or(42);
+        assert_eq!(actual, 1);
+    }
+
+    #[test]
+    fn first_or_else_for_empty_sequence_returns_default() {
+        assert_eq!(empty::<i32>().first_or_else(default_generator), 130);
+    }
+
+    #[test]
+    fn first_or_else_for_singleton_sequence_returns_first_value_from_sequence() {
+        assert_eq!(once(12).first_or_else(default_generator), 12);
+    }
+
+    #[test]
+    fn first_or_else_for_sequence_of_length_two_or_more_returns_first_value_from_sequence() {
+        let actual = (1..3).first_or_else(default_generator);
+        assert_eq!(actual, 1);
+
+        let actual = (1..4).first_or_else(default_generator);
+        assert_eq!(actual, 1);
+    }
+
+    #[test]
+    fn first_or_default_for_empty_sequence_returns_default() {
+        assert_eq!(empty::<i32>().first_or_default(), 0);
+    }
+
+    #[test]
+    fn first_or_default_for_singleton_sequence_returns_first_value_from_sequence() {
+        assert_eq!(once(12).first_or_default(), 12);
+    }
+
+    #[test]
+    fn first_or_default_for_sequence_of_length_two_or_more_returns_first_value_from_sequence() {
+        let actual = (1..3).first_or_default();
+        assert_eq!(actual, 1);
+
+        let actual = (1..4).first_or_default();
+        assert_eq!(actual, 1);
+    }
+
+
+    #[test]
+    fn in_range_works() {
+        let v = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let result = v.into_iter().in_range(0..3).collect::<Vec<_>>();
+        assert_eq!(result, vec![0, 1, 2]);
+
+        let v = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let result = v.into_iter().in_range(4..8).collect::<Vec<_>>();
+        assert_eq!(result, vec![4, 5, 6, 7]);
+
+        // The range is of positions, not values, so it is the same whatever the item type.
+        let v = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let result = v.iter().in_range(4..8).collect::<Vec<_>>();
+        assert_eq!(result, vec![&4, &5, &6, &7]);
+    }
+
+    #[test]
+    fn single_where_for_exactly_one_match_returns_it() {
+        let actual = (1..10).single_where(|&x| x == 4);
+        assert_eq!(actual, Some(4));
+    }
+
+    #[test]
+    fn single_where_for_no_match_returns_none() {
+        let actual = (1..10).single_where(|&x| x > 20);
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn single_where_for_two_matches_returns_none() {
+        let actual = (1..10).single_where(|&x| x % 4 == 0);
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn try_single_where_for_exactly_one_match_returns_ok() {
+        let actual = (1..10).try_single_where(|&x| x == 4);
+        assert_eq!(actual, Ok(4));
+    }
+
+    #[test]
+    fn try_single_where_for_no_match_returns_no_match_error() {
+        let actual = (1..10).try_single_where(|&x| x > 20);
+        assert_eq!(actual, Err(SingleError::NoMatch));
+
+        let actual = empty::<i32>().try_single_where(|_| true);
+        assert_eq!(actual, Err(SingleError::NoMatch));
+    }
+
+    #[test]
+    fn try_single_where_for_two_matches_returns_multiple_matches_error() {
+        let actual = (1..10).try_single_where(|&x| x % 4 == 0);
+        assert_eq!(actual, Err(SingleError::MultipleMatches));
+    }
+
+    #[test]
+    fn last_where_for_multiple_matches_returns_last_match() {
+        let actual = (1..10).last_where(|&x| x % 3 == 0);
+        assert_eq!(actual, Some(9));
+
+        let actual = vec![2, 4, 5, 6, 7].into_iter().last_where(|&x| x % 2 == 0);
+        assert_eq!(actual, Some(6));
+    }
+
+    #[test]
+    fn last_where_for_no_match_returns_none() {
+        let actual = (1..10).last_where(|&x| x > 20);
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn last_where_or_for_no_match_returns_default() {
+        let actual = (1..10).last_where_or(|&x| x > 20, 42);
+        assert_eq!(actual, 42);
+    }
+
+    #[test]
+    fn last_where_or_default_for_no_match_returns_default() {
+        let actual = (1..10).last_where_or_default(|&x| x > 20);
+        assert_eq!(actual, 0);
+    }
+
+    #[test]
+    fn last_where_for_empty_sequence_returns_none_or_default() {
+        assert_eq!(empty::<i32>().last_where(|_| true), None);
+        assert_eq!(empty::<i32>().last_where_or(|_| true, 42), 42);
+        assert_eq!(empty::<i32>().last_where_or_default(|_| true), 0);
+    }
+
+    #[test]
+    fn is_empty_for_empty_sequence_returns_true() {
+        // `Empty` is an `ExactSizeIterator`, which has an unstable `is_empty` method of its own,
+        // so we have to be explicit about which one we mean.
+        assert!(LinqIteratorExtensions::is_empty(&mut empty::<i32>()));
+        assert!((1..10).filter(|&x| x > 20).is_empty());
+    }
+
+    #[test]
+    fn is_empty_for_non_empty_sequence_returns_false_and_consumes_one_element() {
+        let mut source = vec![1, 2, 3].into_iter().filter(|_| true);
+        assert!(!source.is_empty());
+        assert_eq!(source.next(), Some(2));
+    }
+
+    #[test]
+    fn split_into_distributes_extra_elements_to_earlier_parts() {
+        let actual = (1..11).split_into(3);
+        assert_eq!(actual, vec![vec![1, 2, 3, 4], vec![5, 6, 7], vec![8, 9, 10]]);
+    }
+
+    #[test]
+    fn split_into_for_fewer_elements_than_parts_returns_some_empty_parts() {
+        let actual = (1..3).split_into(4);
+        assert_eq!(actual, vec![vec![1], vec![2], vec![], vec![]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "parts must be greater than zero")]
+    fn split_into_for_zero_parts_panics() {
+        (1..3).split_into(0);
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Person {
+        first: &'static str,
+        last: &'static str,
+        age: u32
+    }
+
+    fn person(first: &'static str, last: &'static str, age: u32) -> Person {
+        Person { first, last, age }
+    }
+
+    #[test]
+    fn order_by_many_applies_comparators_in_turn() {
+        let people = vec![
+            person("Fred", "Smith", 40),
+            person("Alice", "Jones", 30),
+            person("Bob", "Smith", 20),
+            person("Carol", "Jones", 50)
+        ];
+
+        let comparators: Vec<BoxedComparer<Person>> = vec![
+            Box::new(|a, b| a.last.cmp(b.last)),
+            Box::new(|a, b| a.age.cmp(&b.age))
+        ];
+
+        let actual = people.into_iter().order_by_many(comparators)
+            .map(|p| p.first)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["Alice", "Carol", "Bob", "Fred"]);
+    }
+
+    #[test]
+    fn order_by_many_only_uses_secondary_comparator_to_break_ties() {
+        let people = vec![
+            person("Fred", "Smith", 20),
+            person("Alice", "Jones", 90),
+            person("Bob", "Adams", 50)
+        ];
+
+        let comparators: Vec<BoxedComparer<Person>> = vec![
+            Box::new(|a, b| a.last.cmp(b.last)),
+            Box::new(|a, b| a.age.cmp(&b.age))
+        ];
+
+        let actual = people.into_iter().order_by_many(comparators)
+            .map(|p| p.first)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["Bob", "Alice", "Fred"]);
+    }
+
+    #[test]
+    fn order_by_many_is_stable() {
+        let people = vec![
+            person("Fred", "Smith", 20),
+            person("Alice", "Jones", 20),
+            person("Bob", "Smith", 20)
+        ];
+
+        let comparators: Vec<BoxedComparer<Person>> = vec![
+            Box::new(|a, b| a.age.cmp(&b.age))
+        ];
+
+        let actual = people.into_iter().order_by_many(comparators)
+            .map(|p| p.first)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["Fred", "Alice", "Bob"]);
+    }
+
+    #[test]
+    fn group_by_bucket_puts_elements_into_sorted_buckets() {
+        let readings = vec![("a", 12.5), ("b", 3.0), ("c", 19.99), ("d", -0.5), ("e", 30.0), ("f", 10.0)];
+        let actual = readings.into_iter().group_by_bucket(10.0, |r| r.1)
+            .into_iter()
+            .map(|(bucket, rs)| (bucket, rs.into_iter().map(|r| r.0).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, vec![
+            (-1, vec!["d"]),
+            (0, vec!["b"]),
+            (1, vec!["a", "c", "f"]),
+            (3, vec!["e"])
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bucket_width must be positive")]
+    fn group_by_bucket_for_non_positive_width_panics() {
+        (1..3).group_by_bucket(0.0, |&x| x as f64);
+    }
+
+    #[test]
+    fn skip_while_counting_returns_number_skipped_and_remainder() {
+        let lines = vec!["", "", "first", "", "second"];
+        let (skipped, rest) = lines.into_iter().skip_while_counting(|s| s.is_empty());
+        assert_eq!(skipped, 2);
+        assert_eq!(rest.collect::<Vec<_>>(), vec!["first", "", "second"]);
+    }
+
+    #[test]
+    fn skip_while_counting_when_first_element_fails_predicate_skips_nothing() {
+        let (skipped, rest) = (1..5).skip_while_counting(|&x| x > 3);
+        assert_eq!(skipped, 0);
+        assert_eq!(rest.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn skip_while_counting_when_everything_matches_skips_everything() {
+        let (skipped, mut rest) = (1..5).skip_while_counting(|_| true);
+        assert_eq!(skipped, 4);
+        assert_eq!(rest.next(), None);
+    }
+
+    #[test]
+    fn reduce_adjacent_by_sums_adjacent_records_with_same_key() {
+        let sales = vec![("a", 1), ("a", 2), ("b", 5), ("c", 1), ("c", 1), ("c", 1)];
+        let actual = sales.into_iter()
+            .reduce_adjacent_by(|s| s.0, |x, y| (x.0, x.1 + y.1))
+            .map(|(k, s)| (k, s.1))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![("a", 3), ("b", 5), ("c", 3)]);
+    }
+
+    #[test]
+    fn reduce_adjacent_by_keeps_non_adjacent_keys_separate() {
+        let sales = vec![("a", 1), ("b", 2), ("a", 3), ("a", 4)];
+        let actual = sales.into_iter()
+            .reduce_adjacent_by(|s| s.0, |x, y| (x.0, x.1 + y.1))
+            .map(|(k, s)| (k, s.1))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![("a", 1), ("b", 2), ("a", 7)]);
+    }
+
+    #[test]
+    fn reduce_adjacent_by_for_empty_sequence_yields_nothing() {
+        let actual = empty::<i32>().reduce_adjacent_by(|&x| x, |x, y| x + y).next();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn chunks_rev_returns_chunks_last_first_with_ragged_chunk_last() {
+        let actual = (1..8).chunks_rev(3);
+        assert_eq!(actual, vec![vec![5, 6, 7], vec![2, 3, 4], vec![1]]);
+    }
+
+    #[test]
+    fn chunks_rev_for_exact_multiple_has_no_ragged_chunk() {
+        let actual = (1..7).chunks_rev(3);
+        assert_eq!(actual, vec![vec![4, 5, 6], vec![1, 2, 3]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "size must be greater than zero")]
+    fn chunks_rev_for_zero_size_panics() {
+        (1..8).chunks_rev(0);
+    }
+
+    #[test]
+    fn distinct_reporting_returns_distinct_elements_and_dropped_positions() {
+        let source = vec!["a", "b", "a", "c", "b", "a", "d"];
+        let (distinct, dropped) = source.into_iter().distinct_reporting();
+        assert_eq!(distinct, vec!["a", "b", "c", "d"]);
+        assert_eq!(dropped, vec![2, 4, 5]);
+    }
+
+    #[test]
+    fn distinct_reporting_for_distinct_sequence_drops_nothing() {
+        let (distinct, dropped) = (1..5).distinct_reporting();
+        assert_eq!(distinct, vec![1, 2, 3, 4]);
+        assert!(dropped.is_empty());
+    }
+
+    // std has an unstable `Iterator::map_windows`, so to avoid the name clash we call ours
+    // through the trait.
+    #[test]
+    fn map_windows_applies_function_to_each_window() {
+        let actual = LinqIteratorExtensions::map_windows(1..6, 3, |w| w.iter().sum::<i32>())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![6, 9, 12]);
+    }
+
+    #[test]
+    fn map_windows_yields_len_minus_size_plus_one_outputs() {
+        let source = [1, 2, 3, 4, 5, 6, 7, 8];
+        for size in 1..9 {
+            let actual = LinqIteratorExtensions::map_windows(source.iter(), size, |w| w.len()).count();
+            assert_eq!(actual, source.len() - size + 1);
+        }
+
+        let actual = LinqIteratorExtensions::map_windows(source.iter(), 9, |w| w.len()).count();
+        assert_eq!(actual, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "size must be greater than zero")]
+    fn map_windows_for_zero_size_panics() {
+        LinqIteratorExtensions::map_windows(1..6, 0, |w| w.len());
+    }
+
+    #[test]
+    fn replace_at_replaces_middle_element() {
+        let actual = (1..6).replace_at(2, 42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 42, 4, 5]);
+    }
+
+    #[test]
+    fn replace_at_replaces_first_element() {
+        let actual = (1..6).replace_at(0, 42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![42, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn replace_at_for_out_of_range_index_yields_source_unchanged() {
+        let actual = (1..6).replace_at(5, 42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn insert_at_start_prepends_value() {
+        let actual = (1..4).insert_at(0, 42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![42, 1, 2, 3]);
+    }
+
+    #[test]
+    fn insert_at_middle_shifts_subsequent_elements() {
+        let actual = (1..4).insert_at(1, 42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 42, 2, 3]);
+    }
+
+    #[test]
+    fn insert_at_end_appends_value() {
+        let actual = (1..4).insert_at(3, 42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 42]);
+    }
+
+    #[test]
+    fn insert_at_beyond_end_appends_value() {
+        let actual = (1..4).insert_at(10, 42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 42]);
+    }
+
+    #[test]
+    fn remove_at_removes_first_element() {
+        let actual = (1..6).remove_at(0).collect::<Vec<_>>();
+        assert_eq!(actual, vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn remove_at_removes_middle_element() {
+        let actual = (1..6).remove_at(2).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn remove_at_removes_last_element() {
+        let actual = (1..6).remove_at(4).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn remove_at_for_out_of_range_index_yields_source_unchanged() {
+        let actual = (1..6).remove_at(5).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn generate_yields_powers_of_two_up_to_bound() {
+        let actual = generate(1, |&x| if x < 64 { Some(x * 2) } else { None }).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 4, 8, 16, 32, 64]);
+    }
+
+    #[test]
+    fn generate_stops_when_step_returns_none() {
+        let actual = generate(10u32, |&x| x.checked_sub(4)).collect::<Vec<_>>();
+        assert_eq!(actual, vec![10, 6, 2]);
+
+        let actual = generate(10, |_| None).collect::<Vec<_>>();
+        assert_eq!(actual, vec![10]);
+    }
+
+    #[test]
+    fn ungroup_flattens_key_and_items_pairs() {
+        let groups = vec![(1, vec!["a", "b"]), (2, vec!["c"])];
+        let actual = groups.into_iter().ungroup().collect::<Vec<_>>();
+        assert_eq!(actual, vec![(1, "a"), (1, "b"), (2, "c")]);
+    }
+
+    #[test]
+    fn ungroup_skips_empty_groups() {
+        let groups = vec![(1, vec![]), (2, vec!["c"]), (3, vec![])];
+        let actual = groups.into_iter().ungroup().collect::<Vec<_>>();
+        assert_eq!(actual, vec![(2, "c")]);
+    }
+
+    #[test]
+    fn count_by_index_range_counts_elements_per_block() {
+        let actual = (1..21).filter(|x| x % 2 == 0).count_by_index_range(4);
+        assert_eq!(actual, vec![4, 4, 2]);
+
+        let actual = (1..21).filter(|x| x % 5 == 0).count_by_index_range(2);
+        assert_eq!(actual, vec![2, 2]);
+    }
+
+    #[test]
+    fn count_by_index_range_for_empty_sequence_returns_no_counts() {
+        let actual = (1..21).filter(|&x| x > 30).count_by_index_range(4);
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "bucket_size must be greater than zero")]
+    fn count_by_index_range_for_zero_bucket_size_panics() {
+        (1..21).count_by_index_range(0);
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Order {
+        id: u32,
+        lines: Vec<&'static str>
+    }
+
+    #[test]
+    fn select_many_with_pairs_each_inner_item_with_its_outer_element() {
+        let orders = vec![
+            Order { id: 1, lines: vec!["apples", "pears"] },
+            Order { id: 2, lines: vec![] },
+            Order { id: 3, lines: vec!["plums"] }
+        ];
+
+        let actual = orders.into_iter()
+            .select_many_with(|o| o.lines.clone(), |o, line| (o.id, line))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![(1, "apples"), (1, "pears"), (3, "plums")]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct LogRecord {
+        level: String,
+        message: String
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn write_json_lines_writes_one_object_per_line() {
+        let records = vec![
+            LogRecord { level: "INFO".to_string(), message: "started".to_string() },
+            LogRecord { level: "WARN".to_string(), message: "disk \"nearly\" full\n".to_string() }
+        ];
+
+        let mut buffer = Vec::new();
+        records.iter().write_json_lines(&mut buffer).unwrap();
+
+        let text = String::from_utf8(buffer).unwrap();
+        let actual = text.lines()
+            .map(|line| serde_json::from_str::<LogRecord>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, records);
+        assert!(text.ends_with('\n'));
+    }
+
+    #[test]
+    fn top_n_per_group_keeps_largest_n_per_group_in_key_order() {
+        let scores = vec![("maths", 70), ("art", 55), ("maths", 90), ("art", 80), ("maths", 85), ("music", 40)];
+        let actual = scores.into_iter()
+            .map(|(subject, score)| (score, subject))
+            .top_n_per_group(2, |s| s.1)
+            .into_iter()
+            .map(|(k, ss)| (k, ss.into_iter().map(|s| s.0).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, vec![
+            ("maths", vec![90, 85]),
+            ("art", vec![80, 55]),
+            ("music", vec![40])
+        ]);
+    }
+
+    #[test]
+    fn top_n_per_group_by_ranks_by_projection() {
+        let scores = vec![("maths", 70), ("art", 55), ("maths", 90), ("art", 80), ("maths", 85)];
+        let actual = scores.into_iter().top_n_per_group_by(2, |s| s.0, |s| s.1);
+
+        assert_eq!(actual, vec![
+            ("maths", vec![("maths", 90), ("maths", 85)]),
+            ("art", vec![("art", 80), ("art", 55)])
+        ]);
+    }
+
+    #[test]
+    fn top_n_per_group_with_zero_n_keeps_empty_groups() {
+        let actual = (1..5).top_n_per_group(0, |x| x % 2);
+        assert_eq!(actual, vec![(1, vec![]), (0, vec![])]);
+    }
+
+    #[test]
+    fn interleave_weighted_draws_according_to_weights() {
+        let actual = vec![1, 2, 3, 4, 5, 6].into_iter()
+            .interleave_weighted(vec![10, 20, 30], 2, 1)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 10, 3, 4, 20, 5, 6, 30]);
+    }
+
+    #[test]
+    fn interleave_weighted_continues_with_remaining_sequence() {
+        let actual = vec![1, 2, 3].into_iter()
+            .interleave_weighted(vec![10, 20, 30, 40, 50], 2, 1)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 10, 3, 20, 30, 40, 50]);
+
+        let actual = vec![1, 2, 3, 4, 5, 6].into_iter()
+            .interleave_weighted(vec![10], 1, 2)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 10, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "weights must be greater than zero")]
+    fn interleave_weighted_for_zero_weight_panics() {
+        (1..3).interleave_weighted(4..6, 0, 1);
+    }
+
+    #[test]
+    fn aggregate_segments_folds_each_segment() {
+        let source = vec![0, 1, 2, 0, 5, 0, 0, 3, 3];
+        let actual = source.into_iter()
+            .aggregate_segments(|&x| x == 0, 0, |acc, x| acc + x)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![3, 5, 0, 6]);
+    }
+
+    #[test]
+    fn aggregate_segments_treats_leading_elements_as_a_segment() {
+        let source = vec![4, 4, -1, 1, 2];
+        let actual = source.into_iter()
+            .aggregate_segments(|&x| x < 0, Vec::new(), |mut acc, x| { acc.push(x); acc })
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![4, 4], vec![-1, 1, 2]]);
+    }
+
+    #[test]
+    fn aggregate_segments_for_empty_sequence_yields_nothing() {
+        let actual = empty::<i32>().aggregate_segments(|&x| x == 0, 0, |acc, x| acc + x).next();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn peek_while_collects_leading_run_and_keeps_boundary_element() {
+        let mut source = vec![1, 2, 3, 10, 4, 5].into_iter().peekable_linq();
+        assert_eq!(source.peek_while(|&x| x < 5), vec![1, 2, 3]);
+        assert_eq!(source.next(), Some(10));
+        assert_eq!(source.peek_while(|&x| x < 5), vec![4]);
+        assert_eq!(source.collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[test]
+    fn peek_while_when_first_element_fails_returns_empty() {
+        let mut source = (10..13).peekable_linq();
+        assert!(source.peek_while(|&x| x < 5).is_empty());
+        assert_eq!(source.peek(), Some(&10));
+        assert_eq!(source.next(), Some(10));
+    }
+
+    #[test]
+    fn gaps_between_yields_matches_with_gap_since_previous_match() {
+        let events = vec!["-", "-", "A", "-", "-", "B", "C", "-", "D", "-"];
+        let actual = events.into_iter().gaps_between(|&e| e != "-").collect::<Vec<_>>();
+        assert_eq!(actual, vec![("A", 2), ("B", 2), ("C", 0), ("D", 1)]);
+    }
+
+    #[test]
+    fn gaps_between_for_first_element_match_has_zero_gap() {
+        let actual = (0..10).gaps_between(|x| x % 4 == 0).collect::<Vec<_>>();
+        assert_eq!(actual, vec![(0, 0), (4, 3), (8, 3)]);
+    }
+
+    #[test]
+    fn to_fixed_array_for_exact_length_returns_array() {
+        let actual = "1,2,3".split(',').to_fixed_array::<3>();
+        assert_eq!(actual, Ok(["1", "2", "3"]));
+    }
+
+    #[test]
+    fn to_fixed_array_for_too_few_elements_returns_elements_in_error() {
+        let actual = "1,2".split(',').to_fixed_array::<3>();
+        assert_eq!(actual, Err(vec!["1", "2"]));
+    }
+
+    #[test]
+    fn to_fixed_array_for_too_many_elements_returns_error() {
+        let actual = "1,2,3,4,5".split(',').to_fixed_array::<3>();
+        assert_eq!(actual, Err(vec!["1", "2", "3", "4"]));
+    }
+
+    #[test]
+    fn weighted_average_computes_weighted_mean() {
+        let grades = vec![(90.0, 3.0), (70.0, 1.0)];
+        let actual = grades.into_iter().weighted_average(|g| g.0, |g| g.1);
+        assert_eq!(actual, Some(85.0));
+
+        let ratings = vec![(5, 10), (4, 20), (1, 10)];
+        let actual = ratings.into_iter().weighted_average(|r| r.0 as f64, |r| r.1 as f64);
+        assert_eq!(actual, Some(3.5));
+    }
+
+    #[test]
+    fn weighted_average_for_zero_total_weight_returns_none() {
+        let grades = vec![(90.0, 0.0), (70.0, 0.0)];
+        let actual = grades.into_iter().weighted_average(|g| g.0, |g| g.1);
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn weighted_average_for_empty_sequence_returns_none() {
+        let actual = empty::<(f64, f64)>().weighted_average(|g| g.0, |g| g.1);
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn distinct_recent_drops_repeat_within_window() {
+        let actual = vec!["a", "b", "a", "b", "c"].into_iter().distinct_recent(2).collect::<Vec<_>>();
+        assert_eq!(actual, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn distinct_recent_passes_repeat_after_capacity_other_values() {
+        let actual = vec!["a", "b", "c", "a"].into_iter().distinct_recent(2).collect::<Vec<_>>();
+        assert_eq!(actual, vec!["a", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn distinct_recent_refreshes_element_when_seen_again() {
+        // The second "a" is dropped but makes "a" the most recent, so "b" is evicted by "c".
+        let actual = vec!["a", "b", "a", "c", "a", "b"].into_iter().distinct_recent(2).collect::<Vec<_>>();
+        assert_eq!(actual, vec!["a", "b", "c", "b"]);
+    }
+
+    #[test]
+    fn distinct_recent_handles_large_capacity_with_many_repeats() {
+        // Every value repeats many times inside the window, so only the first of each survives.
+        let actual = (0..200_000).map(|i| i % 1000).distinct_recent(10_000).collect::<Vec<_>>();
+        assert_eq!(actual, (0..1000).collect::<Vec<_>>());
+
+        // With a window smaller than the cycle every value has been evicted before it comes round again.
+        let actual = (0..20_000).map(|i| i % 1000).distinct_recent(999).count();
+        assert_eq!(actual, 20_000);
+    }
+
+    #[test]
+    fn distinct_recent_with_zero_capacity_drops_nothing() {
+        let actual = vec![1, 1, 2, 1].into_iter().distinct_recent(0).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn order_by_merge_sorts_by_key() {
+        let actual = vec![5, 3, 9, 1, 4, 1, 0].into_iter().order_by_merge(|&x| x).collect::<Vec<_>>();
+        assert_eq!(actual, vec![0, 1, 1, 3, 4, 5, 9]);
+
+        let actual = empty::<i32>().order_by_merge(|&x| x).next();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn order_by_merge_is_stable_for_equal_keys() {
+        // Lots of ties: sort (position, key) pairs by key only, and check the positions within
+        // each key are still ascending, exactly as the (stable) std sort leaves them.
+        let source = (0..1000).map(|i| (i, (i * 7919) % 13)).collect::<Vec<_>>();
+
+        let actual = source.clone().into_iter().order_by_merge(|&(_, key)| key).collect::<Vec<_>>();
+
+        let mut expected = source;
+        expected.sort_by_key(|&(_, key)| key);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn partition_three_classifies_against_pivot() {
+        let source = vec![5, 1, 7, 5, 3, 9, 5, 2];
+        let (less, equal, greater) = source.into_iter().partition_three(|x| x.cmp(&5));
+        assert_eq!(less, vec![1, 3, 2]);
+        assert_eq!(equal, vec![5, 5, 5]);
+        assert_eq!(greater, vec![7, 9]);
+    }
+
+    #[test]
+    fn partition_three_for_empty_sequence_returns_empty_buckets() {
+        let (less, equal, greater) = empty::<i32>().partition_three(|x| x.cmp(&5));
+        assert!(less.is_empty() && equal.is_empty() && greater.is_empty());
+    }
+
+    #[test]
+    fn collect_string_builds_string_from_chars() {
+        let source = "Hello, World!";
+        let actual = source.chars().filter(|c| c.is_alphabetic()).collect_string();
+        let expected = source.chars().filter(|c| c.is_alphabetic()).collect::<String>();
+        assert_eq!(actual, "HelloWorld");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn collect_string_collects_exact_size_source() {
+        let actual = "abcdefghij".chars().collect::<Vec<_>>().into_iter().collect_string();
+        assert_eq!(actual, "abcdefghij");
+        assert!(actual.capacity() >= actual.len());
+    }
+
+    #[test]
+    fn collect_string_from_str_concatenates_slices() {
+        let source = ["red", "green", "blue"];
+        let actual = source.iter().cloned().collect_string_from_str();
+        assert_eq!(actual, "redgreenblue");
+        assert_eq!(actual, source.concat());
+    }
+
+    #[test]
+    fn window_padded_yields_one_output_per_input() {
+        let actual = vec![1, 2, 3, 4, 5].into_iter()
+            .window_padded(3, |w| w.to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![
+            vec![1, 2],
+            vec![1, 2, 3],
+            vec![2, 3, 4],
+            vec![3, 4, 5],
+            vec![4, 5]
+        ]);
+    }
+
+    #[test]
+    fn window_padded_computes_smoothed_series() {
+        let actual = vec![3.0, 6.0, 9.0, 3.0].into_iter()
+            .window_padded(3, |w| w.iter().sum::<f64>() / w.len() as f64)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![4.5, 6.0, 6.0, 6.0]);
+    }
+
+    #[test]
+    fn window_padded_for_short_sequence_uses_whole_sequence() {
+        let actual = vec![1, 2].into_iter().window_padded(5, |w| w.len()).collect::<Vec<_>>();
+        assert_eq!(actual, vec![2, 2]);
+
+        let actual = empty::<i32>().window_padded(3, |w| w.len()).next();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn window_padded_for_even_size_takes_extra_element_after() {
+        let actual = (1..5).window_padded(2, |w| w.to_vec()).collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![1, 2], vec![2, 3], vec![3, 4], vec![4]]);
+    }
+
+    #[test]
+    fn map_stateful_numbers_elements() {
+        let actual = vec!["a", "b", "c"].into_iter()
+            .map_stateful(1, |n, s| {
+                let numbered = format!("{}. {}", n, s);
+                *n += 1;
+                numbered
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["1. a", "2. b", "3. c"]);
+    }
+
+    #[test]
+    fn map_stateful_computes_running_transformation() {
+        // The difference from the previous element, treating the start as zero.
+        let actual = vec![3, 7, 8, 20].into_iter()
+            .map_stateful(0, |prev, x| {
+                let delta = x - *prev;
+                *prev = x;
+                delta
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![3, 4, 1, 12]);
+    }
+
+    #[test]
+    fn intersect_yields_distinct_common_elements_in_order_of_first_sequence() {
+        let actual = vec![1, 2, 2, 3].into_iter().intersect(vec![2, 3, 4]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![2, 3]);
+
+        let actual = vec![5, 3, 1, 3, 5].into_iter().intersect(vec![1, 3, 5]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![5, 3, 1]);
+    }
+
+    #[test]
+    fn intersect_with_empty_second_sequence_yields_nothing() {
+        let actual = vec![1, 2, 3].into_iter().intersect(vec![]).collect::<Vec<_>>();
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn intersect_works_with_string_slices() {
+        let first = vec!["red", "green", "blue", "white"];
+        let second = ["white", "black", "red"];
+        let actual = first.into_iter().intersect(second.iter().cloned()).collect::<Vec<_>>();
+        assert_eq!(actual, vec!["red", "white"]);
+    }
+
+    #[test]
+    fn except_yields_distinct_elements_not_in_second_sequence() {
+        let actual = vec![1, 1, 2, 3].into_iter().except(vec![1]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![2, 3]);
+
+        let actual = vec![5, 4, 3, 4, 2, 1].into_iter().except(vec![3, 1, 7]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![5, 4, 2]);
+    }
+
+    #[test]
+    fn except_with_empty_second_sequence_yields_distinct_first_sequence() {
+        let actual = vec![3, 1, 3, 2, 1].into_iter().except(vec![]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn except_everything_yields_nothing() {
+        let actual = vec![1, 2, 2, 3].into_iter().except(vec![3, 2, 1]).collect::<Vec<_>>();
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn union_of_overlapping_sequences_yields_each_element_once() {
+        let actual = vec![1, 2, 3].into_iter().union(vec![2, 3, 4]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn union_of_disjoint_sequences_yields_both() {
+        let actual = vec![1, 2].into_iter().union(vec![3, 4]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn union_removes_duplicates_within_first_sequence() {
+        let actual = vec![1, 1, 2, 1].into_iter().union(vec![3, 2, 3]).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn union_is_lazy() {
+        // The second sequence is infinite, but we only need to read a little of it.
+        let actual = vec![1, 2].into_iter().union(1..).take(4).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn distinct_yields_elements_in_order_of_first_appearance() {
+        let actual = vec![1, 1, 2, 1, 3, 2].into_iter().distinct().collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn distinct_for_distinct_sequence_yields_it_unchanged() {
+        let actual = vec!["c", "a", "b"].into_iter().distinct().collect::<Vec<_>>();
+        assert_eq!(actual, vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn distinct_by_keeps_first_element_per_key() {
+        let source = vec![("a", 1), ("b", 2), ("a", 3)];
+        let actual = source.into_iter().distinct_by(|x| x.0).collect::<Vec<_>>();
+        assert_eq!(actual, vec![("a", 1), ("b", 2)]);
+    }
+
+    #[test]
+    fn distinct_by_only_requires_key_to_be_hashable() {
+        // f64 is not Hash, so neither is Measurement.
+        #[derive(Debug, PartialEq)]
+        struct Measurement {
+            sensor: u32,
+            value: f64
+        }
+
+        let source = vec![
+            Measurement { sensor: 1, value: 0.5 },
+            Measurement { sensor: 2, value: 1.5 },
+            Measurement { sensor: 1, value: 2.5 }
+        ];
+
+        let actual = source.into_iter().distinct_by(|m| m.sensor).map(|m| m.value).collect::<Vec<_>>();
+        assert_eq!(actual, vec![0.5, 1.5]);
+    }
+
+    #[test]
+    fn group_by_groups_numbers_by_parity() {
+        let groups = vec![3, 4, 1, 6, 8, 5].into_iter().group_by(|x| x % 2 == 0);
+
+        assert_eq!(groups.len(), 2);
+        assert!(!groups[0].key);
+        assert_eq!(groups[0].elements, vec![3, 1, 5]);
+        assert!(groups[1].key);
+        assert_eq!(groups[1].elements, vec![4, 6, 8]);
+    }
+
+    #[test]
+    fn group_by_groups_words_by_first_letter() {
+        let words = vec!["blueberry", "chimpanzee", "abacus", "banana", "apple", "cheese"];
+        let groups = words.into_iter().group_by(|w| w.chars().next());
+
+        let keys = groups.iter().map(|g| g.key).collect::<Vec<_>>();
+        assert_eq!(keys, vec![Some('b'), Some('c'), Some('a')]);
+
+        let members = groups.into_iter().map(|g| g.into_iter().collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(members, vec![
+            vec!["blueberry", "banana"],
+            vec!["chimpanzee", "cheese"],
+            vec!["abacus", "apple"]
+        ]);
+    }
+
+    #[test]
+    fn grouping_can_be_iterated_by_reference() {
+        let groups = (1..7).group_by(|x| x % 3);
+        let sums = groups.iter().map(|g| g.into_iter().sum::<i32>()).collect::<Vec<_>>();
+        assert_eq!(sums, vec![5, 7, 9]);
+    }
+
+    #[test]
+    fn lookup_get_for_present_key_returns_elements() {
+        let lookup = vec!["apple", "avocado", "banana"].into_iter().to_lookup(|w| w.chars().next());
+        assert_eq!(lookup.get(&Some('a')), &["apple", "avocado"]);
+        assert_eq!(lookup.get(&Some('b')), &["banana"]);
+        assert!(lookup.contains_key(&Some('a')));
+        assert_eq!(lookup.len(), 2);
+    }
+
+    #[test]
+    fn lookup_get_for_absent_key_returns_empty_slice() {
+        let lookup = vec!["apple", "avocado", "banana"].into_iter().to_lookup(|w| w.chars().next());
+        assert!(lookup.get(&Some('z')).is_empty());
+        assert!(!lookup.contains_key(&Some('z')));
+
+        let lookup = empty::<i32>().to_lookup(|&x| x);
+        assert!(lookup.is_empty());
+        assert!(lookup.get(&1).is_empty());
+    }
+
+    #[test]
+    fn lookup_can_iterate_all_groupings() {
+        let lookup = (1..10).to_lookup(|x| x % 3);
+        let mut groupings = lookup.iter().map(|(&k, v)| (k, v.to_vec())).collect::<Vec<_>>();
+        groupings.sort();
+        assert_eq!(groupings, vec![(0, vec![3, 6, 9]), (1, vec![1, 4, 7]), (2, vec![2, 5, 8])]);
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Customer {
+        id: u32,
+        name: &'static str
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct CustomerOrder {
+        customer_id: u32,
+        product: &'static str
+    }
+
+    fn customers() -> Vec<Customer> {
+        vec![
+            Customer { id: 1, name: "Alice" },
+            Customer { id: 2, name: "Bob" },
+            Customer { id: 3, name: "Carol" }
+        ]
+    }
+
+    fn customer_orders() -> Vec<CustomerOrder> {
+        vec![
+            CustomerOrder { customer_id: 3, product: "pen" },
+            CustomerOrder { customer_id: 1, product: "book" },
+            CustomerOrder { customer_id: 4, product: "lamp" },
+            CustomerOrder { customer_id: 3, product: "ink" },
+            CustomerOrder { customer_id: 3, product: "paper" }
+        ]
+    }
+
+    #[test]
+    fn join_matches_outer_elements_to_inner_elements_by_key() {
+        let actual = customer_orders().into_iter()
+            .join(customers(), |o| o.customer_id, |c| c.id, |o, c| (o.product, c.name))
+            .collect::<Vec<_>>();
+
+        // The order for customer 4 has no match, so produces nothing.
+        assert_eq!(actual, vec![("pen", "Carol"), ("book", "Alice"), ("ink", "Carol"), ("paper", "Carol")]);
+    }
+
+    #[test]
+    fn join_yields_every_matching_inner_element_in_order() {
+        let actual = customers().into_iter()
+            .join(customer_orders(), |c| c.id, |o| o.customer_id, |c, o| (c.name, o.product))
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, vec![("Alice", "book"), ("Carol", "pen"), ("Carol", "ink"), ("Carol", "paper")]);
+    }
+
+    #[test]
+    fn group_join_produces_one_result_per_outer_element() {
+        let departments = vec![(1, "Sales"), (2, "Research"), (3, "Admin")];
+        let employees = vec![("Ann", 2), ("Bill", 1), ("Cath", 2), ("Dave", 2)];
+
+        let actual = departments.into_iter()
+            .group_join(employees, |d| d.0, |e| e.1, |d, es| {
+                (d.1, es.iter().map(|e| e.0).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, vec![
+            ("Sales", vec!["Bill"]),
+            ("Research", vec!["Ann", "Cath", "Dave"]),
+            ("Admin", vec![])
+        ]);
+    }
+
+    #[test]
+    fn order_by_then_by_sorts_by_last_name_then_first_name() {
+        let people = vec![
+            person("Fred", "Smith", 40),
+            person("Alice", "Jones", 30),
+            person("Bob", "Smith", 20),
+            person("Carol", "Adams", 50),
+            person("Adam", "Jones", 60)
+        ];
+
+        let actual = people.into_iter()
+            .order_by(|p| p.last)
+            .then_by(|p| p.first)
+            .map(|p| format!("{} {}", p.first, p.last))
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, vec!["Carol Adams", "Adam Jones", "Alice Jones", "Bob Smith", "Fred Smith"]);
+    }
+
+    #[test]
+    fn order_by_descending_then_by_descending_reverses_each_key() {
+        let people = vec![
+            person("Fred", "Smith", 40),
+            person("Alice", "Jones", 30),
+            person("Bob", "Smith", 20),
+            person("Carol", "Adams", 50)
+        ];
+
+        let actual = people.into_iter()
+            .order_by_descending(|p| p.last)
+            .then_by_descending(|p| p.age)
+            .map(|p| p.first)
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, vec!["Fred", "Bob", "Alice", "Carol"]);
+    }
+
+    #[test]
+    fn order_by_is_stable() {
+        let people = vec![
+            person("Fred", "Smith", 40),
+            person("Alice", "Jones", 30),
+            person("Bob", "Smith", 20),
+            person("Carol", "Jones", 50)
+        ];
+
+        let actual = people.into_iter()
+            .order_by(|p| p.last)
+            .map(|p| p.first)
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, vec!["Alice", "Carol", "Fred", "Bob"]);
+    }
+
+    #[test]
+    fn aggregate_sums_ints() {
+        assert_eq!((1..5).aggregate(|acc, x| acc + x), Some(10));
+        assert_eq!(once(7).aggregate(|acc, x| acc + x), Some(7));
+    }
+
+    #[test]
+    fn aggregate_for_empty_sequence_returns_none() {
+        assert_eq!(empty::<i32>().aggregate(|acc, x| acc + x), None);
+    }
+
+    #[test]
+    fn aggregate_with_builds_comma_joined_string() {
+        let actual = vec!["red", "green", "blue"].into_iter()
+            .aggregate_with(String::new(), |mut acc, s| {
+                if !acc.is_empty() {
+                    acc.push_str(", ");
+                }
+                acc.push_str(s);
+                acc
+            });
+        assert_eq!(actual, "red, green, blue");
+    }
+
+    #[test]
+    fn aggregate_select_applies_result_selector_to_accumulator() {
+        let actual = vec!["red", "green", "blue"].into_iter()
+            .aggregate_select(String::new(), |acc, s| acc + s, |acc| acc.len());
+        assert_eq!(actual, 12);
+    }
+
+    #[test]
+    fn sum_linq_sums_elements() {
+        assert_eq!((1..5).sum_linq(), 10);
+        assert_eq!(empty::<i32>().sum_linq(), 0);
+        assert_eq!(vec![0.5, 1.5].into_iter().sum_linq(), 2.0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overflow")]
+    fn sum_linq_overflow_panics_in_debug_builds() {
+        vec![i32::MAX - 1, 1, 1].into_iter().sum_linq();
+    }
+
+    #[test]
+    fn average_computes_mean() {
+        assert_eq!(vec![1, 2, 3, 4].into_iter().average(), Some(2.5));
+    }
+
+    #[test]
+    fn average_for_empty_sequence_returns_none() {
+        assert_eq!(empty::<i32>().average(), None);
+    }
+
+    #[test]
+    fn average_does_not_overflow() {
+        let actual = vec![i32::MAX, i32::MAX, i32::MAX].into_iter().average();
+        assert_eq!(actual, Some(i32::MAX as f64));
+    }
+
+    #[test]
+    fn average_accepts_64_bit_integers() {
+        assert_eq!([i64::MAX, i64::MAX].iter().cloned().average(), Some(i64::MAX as f64));
+        assert_eq!([u64::MAX, 0].iter().cloned().average(), Some(u64::MAX as f64 / 2.0));
+        assert_eq!([1usize, 2].iter().cloned().average(), Some(1.5));
+    }
+
+    #[test]
+    fn max_by_key_linq_finds_longest_word() {
+        let words = vec!["red", "yellow", "green", "blue"];
+        assert_eq!(words.into_iter().max_by_key_linq(|w| w.len()), Some("yellow"));
+    }
+
+    #[test]
+    fn min_by_key_linq_finds_youngest_person() {
+        let people = vec![person("Fred", "Smith", 40), person("Alice", "Jones", 30), person("Bob", "Smith", 35)];
+        let actual = people.into_iter().min_by_key_linq(|p| p.age).map(|p| p.first);
+        assert_eq!(actual, Some("Alice"));
+    }
+
+    #[test]
+    fn min_and_max_by_key_linq_return_first_element_on_tie() {
+        let words = ["ab", "cd", "efg", "hij", "kl"];
+        assert_eq!(words.iter().min_by_key_linq(|w| w.len()), Some(&"ab"));
+        assert_eq!(words.iter().max_by_key_linq(|w| w.len()), Some(&"efg"));
+    }
+
+    #[test]
+    fn min_and_max_by_key_linq_for_empty_sequence_return_none() {
+        assert_eq!(empty::<i32>().min_by_key_linq(|&x| x), None);
+        assert_eq!(empty::<i32>().max_by_key_linq(|&x| x), None);
+    }
+
+    #[test]
+    fn to_dictionary_maps_keys_to_values() {
+        let people = vec![person("Fred", "Smith", 40), person("Alice", "Jones", 30)];
+        let actual = people.into_iter().to_dictionary(|p| p.first, |p| p.age).unwrap();
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual["Fred"], 40);
+        assert_eq!(actual["Alice"], 30);
+    }
+
+    #[test]
+    fn to_dictionary_for_duplicate_key_returns_error_with_key() {
+        let people = vec![person("Fred", "Smith", 40), person("Alice", "Jones", 30), person("Bob", "Smith", 20)];
+        let actual = people.into_iter().to_dictionary(|p| p.last, |p| p.first);
+        assert_eq!(actual, Err(DuplicateKeyError { key: "Smith" }));
+    }
+
+    #[test]
+    fn to_dictionary_last_wins_overwrites_duplicates() {
+        let people = vec![person("Fred", "Smith", 40), person("Alice", "Jones", 30), person("Bob", "Smith", 20)];
+        let actual = people.into_iter().to_dictionary_last_wins(|p| p.last, |p| p.first);
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual["Smith"], "Bob");
+        assert_eq!(actual["Jones"], "Alice");
+    }
+
+    #[test]
+    fn default_if_empty_for_non_empty_sequence_yields_it_unchanged() {
+        let actual = (1..4).default_if_empty(42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn default_if_empty_for_empty_sequence_yields_default() {
+        let actual = empty::<i32>().default_if_empty(42).collect::<Vec<_>>();
+        assert_eq!(actual, vec![42]);
+    }
+
+    #[test]
+    fn default_if_empty_can_be_chained_before_map() {
+        let actual = (1..4).filter(|&x| x > 5).default_if_empty(0).map(|x| x * 10).collect::<Vec<_>>();
+        assert_eq!(actual, vec![0]);
+
+        let actual = (1..4).default_if_empty(0).map(|x| x * 10).collect::<Vec<_>>();
+        assert_eq!(actual, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn sequence_equal_for_equal_sequences_returns_true() {
+        assert!((1..4).sequence_equal(vec![1, 2, 3]));
+        assert!(empty::<i32>().sequence_equal(vec![]));
+    }
+
+    #[test]
+    fn sequence_equal_for_different_lengths_returns_false() {
+        assert!(!(1..4).sequence_equal(vec![1, 2]));
+        assert!(!(1..3).sequence_equal(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn sequence_equal_for_different_elements_returns_false_without_consuming_rest() {
+        let mut first = vec![1, 2, 3, 4].into_iter();
+        let mut second = vec![1, 5, 3, 4].into_iter();
+        assert!(!first.by_ref().sequence_equal(second.by_ref()));
+        assert_eq!(first.collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(second.collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn sequence_equal_by_uses_custom_comparison() {
+        let first = ["Red", "GREEN", "blue"];
+        let second = ["red", "green", "Blue"];
+        assert!(first.iter().sequence_equal_by(second.iter(), |a, b| a.eq_ignore_ascii_case(b)));
+        assert!(!first.iter().sequence_equal(second.iter()));
+    }
+
+    #[test]
+    fn element_at_for_index_in_range_returns_element() {
+        assert_eq!((10..15).element_at(0), Some(10));
+        assert_eq!((10..15).element_at(3), Some(13));
+        assert_eq!((10..15).element_at_or(3, 42), 13);
+    }
+
+    #[test]
+    fn element_at_for_index_equal_to_length_returns_none_or_default() {
+        assert_eq!((10..15).element_at(5), None);
+        assert_eq!((10..15).element_at_or(5, 42), 42);
+    }
+
+    #[test]
+    fn element_at_zero_for_empty_sequence_returns_none_or_default() {
+        assert_eq!(empty::<i32>().element_at(0), None);
+        assert_eq!(empty::<i32>().element_at_or(0, 42), 42);
+    }
+
+    #[test]
+    fn single_where_works_over_filtered_subset() {
+        let actual = (1..100).filter(|x| x % 10 == 0).single_where(|&x| x > 85);
+        assert_eq!(actual, Some(90));
+
+        let actual = (1..100).filter(|x| x % 10 == 0).single_where(|&x| x > 75);
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn single_where_reads_to_the_end_to_rule_out_a_second_match() {
+        let mut source = 1..10;
+        assert_eq!(source.single_where(|&x| x == 2), Some(2));
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn first_where_returns_first_match() {
+        assert_eq!((1..10).first_where(|x| x % 4 == 0), Some(4));
+        assert_eq!((1..10).first_where_or(|x| x % 4 == 0, 42), 4);
+    }
+
+    #[test]
+    fn first_where_for_no_match_returns_none_or_default() {
+        assert_eq!((1..10).first_where(|&x| x > 20), None);
+        assert_eq!((1..10).first_where_or(|&x| x > 20, 42), 42);
+    }
+
+    #[test]
+    fn first_where_stops_at_the_match() {
+        let mut examined = Vec::new();
+        let mut source = (1..10).inspect(|&x| examined.push(x));
+        assert_eq!(source.first_where(|x| x % 4 == 0), Some(4));
+        drop(source);
+        assert_eq!(examined, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn last_linq_returns_last_element() {
+        assert_eq!(empty::<i32>().last_linq(), None);
+        assert_eq!(once(12).last_linq(), Some(12));
+        assert_eq!((1..4).last_linq(), Some(3));
+    }
+
+    #[test]
+    fn last_or_for_empty_sequence_returns_default() {
+        assert_eq!(empty::<i32>().last_or(42), 42);
+    }
+
+    #[test]
+    fn last_or_for_singleton_sequence_returns_last_value_from_sequence() {
+        assert_eq!(once(12).last_or(42), 12);
+    }
+
+    #[test]
+    fn last_or_for_sequence_of_length_two_or_more_returns_last_value_from_sequence() {
+        let actual = (1..3).last_or(42);
+        assert_eq!(actual, 2);
+
+        let actual = (1..4).last_or(42);
+        assert_eq!(actual, 3);
+    }
+
+    #[test]
+    fn last_or_else_for_empty_sequence_returns_default() {
+        assert_eq!(empty::<i32>().last_or_else(default_generator), 130);
+    }
+
+    #[test]
+    fn last_or_else_for_singleton_sequence_returns_last_value_from_sequence() {
+        assert_eq!(once(12).last_or_else(default_generator), 12);
+    }
+
+    #[test]
+    fn last_or_else_for_sequence_of_length_two_or_more_returns_last_value_from_sequence() {
+        let actual = (1..3).last_or_else(default_generator);
+        assert_eq!(actual, 2);
+
+        let actual = (1..4).last_or_else(default_generator);
+        assert_eq!(actual, 3);
+    }
+
+    #[test]
+    fn last_or_default_for_empty_sequence_returns_default() {
+        assert_eq!(empty::<i32>().last_or_default(), 0);
+    }
+
+    #[test]
+    fn last_or_default_for_singleton_sequence_returns_last_value_from_sequence() {
+        assert_eq!(once(12).last_or_default(), 12);
+    }
+
+    #[test]
+    fn last_or_default_for_sequence_of_length_two_or_more_returns_last_value_from_sequence() {
+        let actual = (1..3).last_or_default();
+        assert_eq!(actual, 2);
+
+        let actual = (1..4).last_or_default();
+        assert_eq!(actual, 3);
+    }
+
+    #[test]
+    fn count_where_counts_matching_elements() {
+        assert_eq!((1..11).count_where(|x| x % 2 == 0), 5);
+        assert_eq!((1..11).long_count_where(|x| x % 2 == 0), 5);
+    }
+
+    #[test]
+    fn count_where_for_no_matches_returns_zero() {
+        assert_eq!((1..11).count_where(|&x| x > 20), 0);
+        assert_eq!((1..11).long_count_where(|&x| x > 20), 0);
+    }
+
+    #[test]
+    fn long_count_counts_elements() {
+        assert_eq!((0..100_000).long_count(), 100_000u64);
+        assert_eq!(empty::<i32>().long_count(), 0);
+    }
+
+    #[test]
+    fn contains_for_present_value_returns_true_and_stops() {
+        let mut source = vec![1, 2, 3, 4].into_iter();
+        assert!(source.contains(&2));
+        assert_eq!(source.next(), Some(3));
+    }
+
+    #[test]
+    fn contains_for_absent_value_returns_false_and_consumes_everything() {
+        let mut source = vec![1, 2, 3, 4].into_iter();
+        assert!(!source.contains(&5));
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn contains_by_uses_custom_comparison() {
+        let colours = ["Red", "GREEN", "blue"];
+        assert!(colours.iter().contains_by(|c| c.eq_ignore_ascii_case("green")));
+        assert!(!colours.iter().contains(&&"green"));
+    }
+
+    #[test]
+    fn reverse_reverses_a_filtered_iterator() {
+        let actual = (1..10).filter(|x| x % 2 == 1).reverse().collect::<Vec<_>>();
+        assert_eq!(actual, vec![9, 7, 5, 3, 1]);
+    }
+
+    #[test]
+    fn reverse_for_empty_sequence_yields_nothing() {
+        assert_eq!(empty::<i32>().reverse().next(), None);
+    }
+
+    #[test]
+    fn reverse_yields_owned_items() {
+        let source = vec!["a".to_string(), "b".to_string()];
+        let actual: Vec<String> = source.into_iter().reverse().collect();
+        assert_eq!(actual, vec!["b".to_string(), "a".to_string()]);
+    }
+
+    #[test]
+    fn zip_with_combines_pairs_of_elements() {
+        let actual = (1..4).zip_with(vec!['a', 'b', 'c'], |n, c| format!("{}{}", n, c)).collect::<Vec<_>>();
+        assert_eq!(actual, vec!["1a", "2b", "3c"]);
+
+        let actual = (1..4).zip_with(4..7, |a, b| a * b).collect::<Vec<_>>();
+        assert_eq!(actual, vec![4, 10, 18]);
+    }
+
+    #[test]
+    fn zip_with_stops_at_shorter_sequence() {
+        let actual = (1..10).zip_with(vec![10, 20], |a, b| a + b).collect::<Vec<_>>();
+        assert_eq!(actual, vec![11, 22]);
+
+        let actual = (1..3).zip_with(vec![10, 20, 30], |a, b| a + b).collect::<Vec<_>>();
+        assert_eq!(actual, vec![11, 22]);
+    }
+
+    #[test]
+    fn zip_with_empty_sequence_yields_nothing() {
+        let actual = (1..10).zip_with(empty::<i32>(), |a, b| a + b).next();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn chunk_yields_chunks_with_short_last_chunk() {
+        let actual = (1..8).chunk(3).collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![1, 2, 3], vec![4, 5, 6], vec![7]]);
+    }
+
+    #[test]
+    fn chunk_for_exact_multiple_yields_full_chunks() {
+        let actual = (1..7).chunk(3).collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![1, 2, 3], vec![4, 5, 6]]);
+    }
+
+    #[test]
+    fn chunk_for_empty_sequence_yields_nothing() {
+        assert_eq!(empty::<i32>().chunk(3).next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "size must be greater than zero")]
+    fn chunk_for_zero_size_panics() {
+        (1..8).chunk(0);
+    }
+
+    #[test]
+    fn windows_yields_overlapping_windows() {
+        let actual = vec![1, 2, 3, 4].into_iter().windows(2).collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![1, 2], vec![2, 3], vec![3, 4]]);
+    }
+
+    #[test]
+    fn windows_of_sequence_length_yields_one_window() {
+        let actual = vec![1, 2, 3, 4].into_iter().windows(4).collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn windows_larger_than_sequence_yields_nothing() {
+        assert_eq!(vec![1, 2, 3, 4].into_iter().windows(5).next(), None);
+    }
+
+    #[test]
+    fn scan_linq_yields_running_sum() {
+        let actual = (1..4).scan_linq(0, |acc, x| acc + x).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 3, 6]);
+    }
+
+    #[test]
+    fn scan_linq_yields_running_max() {
+        let actual = vec![2, 1, 5, 3, 7, 7, 4].into_iter()
+            .scan_linq(i32::MIN, |&acc, x| cmp::max(acc, x))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![2, 2, 5, 5, 7, 7, 7]);
+    }
+
+    #[test]
+    fn scan_linq_for_empty_sequence_yields_nothing() {
+        assert_eq!(empty::<i32>().scan_linq(0, |acc, x| acc + x).next(), None);
+    }
+
+    #[test]
+    fn partition_linq_splits_by_parity() {
+        let (even, odd) = (1..10).partition_linq(|x| x % 2 == 0);
+        assert_eq!(even, vec![2, 4, 6, 8]);
+        assert_eq!(odd, vec![1, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn partition3_splits_by_sign() {
+        let source = vec![3, -1, 0, 5, -7, 0, 2];
+        let (negative, zero, positive) = source.into_iter().partition3(|&x| {
+            if x < 0 { Partition3::Left } else if x == 0 { Partition3::Middle } else { Partition3::Right }
+        });
+        assert_eq!(negative, vec![-1, -7]);
+        assert_eq!(zero, vec![0, 0]);
+        assert_eq!(positive, vec![3, 5, 2]);
+    }
+
+    #[test]
+    fn in_range_with_open_start() {
+        let actual = (10..20).in_range(..3).collect::<Vec<_>>();
+        assert_eq!(actual, vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn in_range_with_open_end() {
+        let actual = (10..20).in_range(7..).collect::<Vec<_>>();
+        assert_eq!(actual, vec![17, 18, 19]);
+    }
+
+    #[test]
+    fn in_range_with_inclusive_range() {
+        let actual = (10..20).in_range(2..=5).collect::<Vec<_>>();
+        assert_eq!(actual, vec![12, 13, 14, 15]);
+    }
+
+    #[test]
+    fn in_range_stops_reading_past_end_of_range() {
+        // The source is infinite, so this would never finish if we kept reading.
+        let actual = (0..).in_range(4..8).collect::<Vec<_>>();
+        assert_eq!(actual, vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn take_last_yields_last_n_elements() {
+        assert_eq!((1..6).take_last(2).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!((1..6).take_last(0).collect::<Vec<_>>(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn skip_last_yields_all_but_last_n_elements() {
+        assert_eq!((1..6).skip_last(2).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!((1..6).skip_last(0).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn take_last_and_skip_last_with_n_larger_than_sequence() {
+        assert_eq!((1..6).take_last(10).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!((1..6).skip_last(10).next(), None);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Circle(u32),
+        Square(u32),
+    }
+
+    fn shapes() -> Vec<Shape> {
+        vec![Shape::Circle(1), Shape::Square(2), Shape::Circle(3), Shape::Square(4)]
+    }
+
+    #[test]
+    fn of_variant_yields_only_matching_variant() {
+        let result = shapes().into_iter()
+            .of_variant(|s| if let Shape::Circle(r) = s { Some(r) } else { None })
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec![1, 3]);
+    }
+
+    #[test]
+    fn of_variant_for_absent_variant_is_empty() {
+        let result = shapes().into_iter()
+            .filter(|s| matches!(s, Shape::Square(_)))
+            .of_variant(|s| if let Shape::Circle(r) = s { Some(r) } else { None })
+            .collect::<Vec<_>>();
+        assert_eq!(result, Vec::<u32>::new());
+    }
+
+    #[test]
+    fn for_each_indexed_passes_zero_based_index() {
+        let mut seen = vec![];
+        ["a", "b", "c"].iter().for_each_indexed(|i, x| seen.push((i, *x)));
+        assert_eq!(seen, vec![(0, "a"), (1, "b"), (2, "c")]);
+    }
+
+    #[test]
+    fn for_each_indexed_over_empty_source_never_calls_closure() {
+        let mut calls = 0;
+        empty::<i32>().for_each_indexed(|_, _| calls += 1);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn sum_by_sums_projected_values() {
+        let words = ["one", "three", "five"];
+        assert_eq!(words.iter().sum_by(|w| w.len()), 12);
+    }
+
+    #[test]
+    fn average_by_averages_projected_field() {
+        let people = [person("Fred", "Smith", 20), person("Jane", "Doe", 31)];
+        assert_eq!(people.iter().average_by(|p| p.age), Some(25.5));
+    }
+
+    #[test]
+    fn average_by_accepts_usize_projection() {
+        let words = ["one", "three", "five"];
+        assert_eq!(words.iter().average_by(|w| w.len()), Some(4.0));
+    }
+
+    #[test]
+    fn average_by_for_empty_sequence_returns_none() {
+        assert_eq!(empty::<&str>().average_by(|w| w.len() as u32), None);
+    }
+
+    #[test]
+    fn distinct_until_changed_collapses_runs() {
+        let result = [1, 1, 2, 2, 2, 1, 3].iter().distinct_until_changed().collect::<Vec<_>>();
+        assert_eq!(result, vec![&1, &2, &1, &3]);
+    }
+
+    #[test]
+    fn distinct_until_changed_without_adjacent_duplicates_is_unchanged() {
+        let result = [1, 2, 1, 2].iter().distinct_until_changed().collect::<Vec<_>>();
+        assert_eq!(result, vec![&1, &2, &1, &2]);
+    }
+
+    #[test]
+    fn distinct_until_changed_by_collapses_by_key() {
+        let people = [person("Fred", "Smith", 20), person("Jane", "Smith", 31),
+                      person("Bill", "Doe", 40), person("Anne", "Smith", 50)];
+        let result = people.iter()
+            .distinct_until_changed_by(|p| p.last)
+            .map(|p| p.first)
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec!["Fred", "Bill", "Anne"]);
+    }
+
+    #[test]
+    fn count_by_counts_by_parity() {
+        let counts = (1..8).count_by(|x| x % 2 == 0);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&true], 3);
+        assert_eq!(counts[&false], 4);
+    }
+
+    #[test]
+    fn count_by_for_empty_sequence_is_empty() {
+        assert!(empty::<i32>().count_by(|x| *x).is_empty());
+    }
+
+    #[test]
+    fn count_by_with_single_key_counts_everything() {
+        let counts = ["apple", "avocado", "apricot"].iter().count_by(|w| w.chars().next());
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[&Some('a')], 3);
+    }
+
+    #[test]
+    fn select_many_flattens_list_of_lists() {
+        let lists = vec![vec![1, 2], vec![], vec![3]];
+        let result = lists.into_iter().select_many(|l| l).collect::<Vec<_>>();
+        assert_eq!(result, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn select_many_projects_each_number_to_a_range() {
+        let result = (1..4).select_many(|n| 0..n).collect::<Vec<_>>();
+        assert_eq!(result, vec![0, 0, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn select_many_indexed_passes_source_index() {
+        let result = ["a", "b"].iter()
+            .select_many_indexed(|i, s| vec![(i, *s); i + 1])
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec![(0, "a"), (1, "b"), (1, "b")]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: u32,
+        name: &'static str
+    }
+
+    fn users() -> Vec<User> {
+        vec![
+            User { id: 1, name: "alice" },
+            User { id: 2, name: "bob" },
+            User { id: 3, name: "carol" },
+            User { id: 2, name: "bob again" }
+        ]
+    }
+
+    #[test]
+    fn intersect_by_keeps_elements_whose_key_is_allowed() {
+        let result = users().into_iter()
+            .intersect_by(vec![3, 1, 99], |u| u.id)
+            .map(|u| u.name)
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec!["alice", "carol"]);
+    }
+
+    #[test]
+    fn intersect_by_yields_duplicate_keys_only_once() {
+        let result = users().into_iter()
+            .intersect_by(vec![2], |u| u.id)
+            .map(|u| u.name)
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec!["bob"]);
+    }
+
+    #[test]
+    fn except_by_removes_elements_whose_key_is_excluded() {
+        let excluded = [User { id: 3, name: "someone else" }];
+        let result = users().into_iter()
+            .except_by(excluded.iter().map(|u| u.id), |u| u.id)
+            .map(|u| u.name)
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn union_by_keeps_left_element_on_key_collision() {
+        let others = vec![User { id: 3, name: "carl" }, User { id: 4, name: "dave" }];
+        let result = users().into_iter()
+            .union_by(others, |u| u.id)
+            .map(|u| u.name)
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec!["alice", "bob", "carol", "dave"]);
+    }
+
+    #[test]
+    fn tee_when_first_fork_is_drained_before_second() {
+        let mut reads = 0;
+        let (a, b) = (1..4).inspect(|_| reads += 1).tee();
+        assert_eq!(a.collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(b.collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(reads, 3);
+    }
+
+    #[test]
+    fn tee_when_forks_are_interleaved() {
+        let (mut a, mut b) = (1..4).tee();
+        assert_eq!(a.next(), Some(1));
+        assert_eq!(b.next(), Some(1));
+        assert_eq!(b.next(), Some(2));
+        assert_eq!(b.next(), Some(3));
+        assert_eq!(a.next(), Some(2));
+        assert_eq!(b.next(), None);
+        assert_eq!(a.next(), Some(3));
+        assert_eq!(a.next(), None);
+    }
+
+    #[test]
+    fn cartesian_yields_every_pair_left_major() {
+        let result = [1, 2].iter().cartesian(['a', 'b'].iter()).collect::<Vec<_>>();
+        assert_eq!(result, vec![(&1, &'a'), (&1, &'b'), (&2, &'a'), (&2, &'b')]);
+    }
+
+    #[test]
+    fn cartesian_with_empty_right_side_is_empty() {
+        assert_eq!((1..4).cartesian(empty::<char>()).next(), None);
+    }
+
+    #[test]
+    fn cartesian_with_longer_right_side_keeps_left_major_order() {
+        let result = (1..3).cartesian(10..13).collect::<Vec<_>>();
+        assert_eq!(result, vec![(1, 10), (1, 11), (1, 12), (2, 10), (2, 11), (2, 12)]);
+    }
+
+    #[test]
+    fn group_adjacent_groups_sorted_sequence_into_runs() {
+        let result = [1, 1, 2, 3, 3, 3].iter().group_adjacent(|x| **x).collect::<Vec<_>>();
+        assert_eq!(result, vec![(1, vec![&1, &1]), (2, vec![&2]), (3, vec![&3, &3, &3])]);
+    }
+
+    #[test]
+    fn group_adjacent_only_groups_adjacent_elements() {
+        let result = [1, 1, 2, 1].iter().group_adjacent(|x| **x).collect::<Vec<_>>();
+        assert_eq!(result, vec![(1, vec![&1, &1]), (2, vec![&2]), (1, vec![&1])]);
+    }
+
+    #[test]
+    fn run_length_encode_counts_runs() {
+        let result = "aaaba".chars().run_length_encode().collect::<Vec<_>>();
+        assert_eq!(result, vec![('a', 3), ('b', 1), ('a', 1)]);
+    }
+
+    #[test]
+    fn run_length_encode_of_distinct_values_gives_counts_of_one() {
+        let result = (1..4).run_length_encode().collect::<Vec<_>>();
+        assert_eq!(result, vec![(1, 1), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn run_length_encode_of_empty_sequence_is_empty() {
+        assert_eq!(empty::<i32>().run_length_encode().next(), None);
+    }
+
+    #[test]
+    fn min_max_finds_both_in_one_pass() {
+        assert_eq!([3, 1, 4, 1, 5, 9, 2].iter().min_max(), Some((&1, &9)));
+    }
+
+    #[test]
+    fn min_max_of_singleton_is_that_element_twice() {
+        assert_eq!(once(7).min_max(), Some((7, 7)));
+    }
+
+    #[test]
+    fn min_max_of_empty_sequence_is_none() {
+        assert_eq!(empty::<i32>().min_max(), None);
+    }
+
+    #[test]
+    fn min_max_by_key_compares_by_projected_field() {
+        let people = [person("Fred", "Smith", 40), person("Alice", "Jones", 30),
+                      person("Bob", "Smith", 45), person("Jane", "Doe", 30)];
+        let (youngest, oldest) = people.iter().min_max_by_key(|p| p.age).unwrap();
+        assert_eq!(youngest.first, "Alice");
+        assert_eq!(oldest.first, "Bob");
+    }
+
+    #[test]
+    fn mode_finds_most_frequent_element() {
+        assert_eq!([1, 2, 2, 3, 2, 1].iter().mode(), Some(&2));
+        assert_eq!([1, 2, 2, 3, 2, 1].iter().mode_all(), vec![&2]);
+    }
+
+    #[test]
+    fn mode_with_tie_prefers_first_appearance() {
+        assert_eq!(["b", "a", "a", "b", "c"].iter().mode(), Some(&"b"));
+        assert_eq!(["b", "a", "a", "b", "c"].iter().mode_all(), vec![&"b", &"a"]);
+    }
+
+    #[test]
+    fn mode_of_all_distinct_sequence() {
+        assert_eq!((1..4).mode(), Some(1));
+        assert_eq!((1..4).mode_all(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn mode_of_empty_sequence() {
+        assert_eq!(empty::<i32>().mode(), None);
+        assert_eq!(empty::<i32>().mode_all(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn median_of_odd_length_sequence_is_middle_value() {
+        assert_eq!([1, 2, 3, 4, 5].iter().cloned().median(), Some(3.0));
+    }
+
+    #[test]
+    fn median_of_even_length_sequence_averages_middle_values() {
+        assert_eq!([1, 2, 3, 4].iter().cloned().median(), Some(2.5));
+    }
+
+    #[test]
+    fn median_sorts_input() {
+        assert_eq!([9.0, 1.0, 5.0, 3.0, 7.0].iter().cloned().median(), Some(5.0));
+    }
+
+    #[test]
+    fn median_of_singleton_and_empty_sequence() {
+        assert_eq!(once(4).median(), Some(4.0));
+        assert_eq!(empty::<i32>().median(), None);
+    }
+
+    #[test]
+    fn percentile_50_is_median() {
+        assert_eq!([4, 1, 3, 2].iter().cloned().percentile(50.0), Some(2.5));
+    }
+
+    #[test]
+    fn percentile_0_and_100_are_min_and_max() {
+        assert_eq!([4, 1, 3, 2].iter().cloned().percentile(0.0), Some(1.0));
+        assert_eq!([4, 1, 3, 2].iter().cloned().percentile(100.0), Some(4.0));
+    }
+
+    #[test]
+    fn percentile_interpolates_between_ranks() {
+        // The 25th percentile of 5 values sits a quarter of the way along, at rank 1.
+        // For 4 values it sits at rank 0.75, between the first and second values.
+        assert_eq!([10, 20, 30, 40, 50].iter().cloned().percentile(25.0), Some(20.0));
+        assert_eq!([10, 20, 30, 40].iter().cloned().percentile(25.0), Some(17.5));
+    }
+
+    #[test]
+    fn percentile_of_empty_sequence_is_none() {
+        assert_eq!(empty::<i32>().percentile(50.0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "p must be between 0 and 100")]
+    fn percentile_out_of_range_panics() {
+        once(1).percentile(101.0);
+    }
+
+    #[test]
+    fn shuffle_with_seeded_rng_gives_known_permutation() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let result = (1..7).shuffle(&mut rng).collect::<Vec<_>>();
+        assert_eq!(result, vec![5, 3, 2, 1, 6, 4]);
+    }
+
+    #[test]
+    fn shuffle_preserves_elements() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut result = [3, 1, 4, 1, 5, 9].iter().shuffle(&mut rng).collect::<Vec<_>>();
+            result.sort();
+            assert_eq!(result, vec![&1, &1, &3, &4, &5, &9]);
+        }
+    }
+
+    #[test]
+    fn sample_with_seeded_rng_is_reproducible() {
+        let first = (1..100).sample(3, &mut StdRng::seed_from_u64(7));
+        let second = (1..100).sample(3, &mut StdRng::seed_from_u64(7));
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, second);
+        assert_eq!(first, vec![79, 58, 42]);
+    }
+
+    #[test]
+    fn sample_with_k_larger_than_source_returns_everything() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!((1..4).sample(10, &mut rng), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn sample_with_k_of_zero_is_empty() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!((1..4).sample(0, &mut rng), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn interleave_alternates_and_appends_longer_tail() {
+        let result = [1, 3, 5].iter().interleave([2, 4].iter()).collect::<Vec<_>>();
+        assert_eq!(result, vec![&1, &2, &3, &4, &5]);
+    }
+
+    #[test]
+    fn interleave_with_empty_second_source_yields_first_unchanged() {
+        let result = (1..4).interleave(empty()).collect::<Vec<_>>();
+        assert_eq!(result, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn interleave_is_lazy() {
+        let mut a_reads = 0;
+        let mut b_reads = 0;
+        {
+            let mut it = (1..100).inspect(|_| a_reads += 1).interleave((1..100).inspect(|_| b_reads += 1));
+            it.next();
+            it.next();
+            it.next();
+        }
+        assert_eq!((a_reads, b_reads), (2, 1));
+    }
+
+    #[test]
+    fn lag_pairs_each_element_with_earlier_one() {
+        let result = [10, 20, 30].iter().cloned().lag(1, 0).collect::<Vec<_>>();
+        assert_eq!(result, vec![(10, 0), (20, 10), (30, 20)]);
+    }
+
+    #[test]
+    fn lead_pairs_each_element_with_later_one() {
+        let result = [10, 20, 30].iter().cloned().lead(1, 0).collect::<Vec<_>>();
+        assert_eq!(result, vec![(10, 20), (20, 30), (30, 0)]);
+        let result = (1..6).lead(2, 0).collect::<Vec<_>>();
+        assert_eq!(result, vec![(1, 3), (2, 4), (3, 5), (4, 0), (5, 0)]);
+    }
+
+    #[test]
+    fn lag_and_lead_with_offset_larger_than_sequence_are_all_fills() {
+        assert_eq!((1..3).lag(5, -1).collect::<Vec<_>>(), vec![(1, -1), (2, -1)]);
+        assert_eq!((1..3).lead(5, -1).collect::<Vec<_>>(), vec![(1, -1), (2, -1)]);
+    }
+
+    #[test]
+    fn lag_and_lead_with_zero_offset_pair_element_with_itself() {
+        assert_eq!((1..3).lag(0, -1).collect::<Vec<_>>(), vec![(1, 1), (2, 2)]);
+        assert_eq!((1..3).lead(0, -1).collect::<Vec<_>>(), vec![(1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn cached_clone_after_partial_consumption_replays_from_start() {
+        let mut first = (1..4).cached();
+        assert_eq!(first.next(), Some(1));
+        assert_eq!(first.next(), Some(2));
+
+        let second = first.clone();
+        assert_eq!(second.collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(first.collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn cached_runs_source_once_per_element() {
+        let calls = std::cell::Cell::new(0);
+        let source = (1..4).map(|x| { calls.set(calls.get() + 1); x * 10 }).cached();
+        let replay = source.clone();
+        assert_eq!(source.collect::<Vec<_>>(), vec![10, 20, 30]);
+        assert_eq!(replay.collect::<Vec<_>>(), vec![10, 20, 30]);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn sequence_compare_shorter_prefix_is_less() {
+        assert_eq!((1..3).sequence_compare(1..4), cmp::Ordering::Less);
+        assert_eq!((1..4).sequence_compare(1..3), cmp::Ordering::Greater);
+    }
+
+    #[test]
+    fn sequence_compare_first_difference_decides() {
+        assert_eq!([1, 5, 0].iter().sequence_compare([1, 6].iter()), cmp::Ordering::Less);
+        assert_eq!("abd".chars().sequence_compare("abc".chars()), cmp::Ordering::Greater);
+    }
+
+    #[test]
+    fn sequence_compare_identical_sequences_are_equal() {
+        assert_eq!((1..4).sequence_compare(vec![1, 2, 3]), cmp::Ordering::Equal);
+        assert_eq!(empty::<i32>().sequence_compare(empty()), cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn flatten_linq_flattens_one_level() {
+        let lists = vec![vec![1, 2], vec![], vec![3]];
+        assert_eq!(lists.into_iter().flatten_linq().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let nested = vec![vec![vec![1], vec![2]], vec![vec![3]]];
+        assert_eq!(nested.into_iter().flatten_linq().collect::<Vec<_>>(), vec![vec![1], vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn flatten_options_drops_nones() {
+        let options = vec![Some(1), None, Some(3)];
+        assert_eq!(options.into_iter().flatten_options().collect::<Vec<_>>(), vec![1, 3]);
+    }
+
+    #[test]
+    fn take_every_yields_every_step_from_offset() {
+        assert_eq!((0..6).take_every(2, 1).collect::<Vec<_>>(), vec![1, 3, 5]);
+        assert_eq!((0..10).take_every(3, 2).collect::<Vec<_>>(), vec![2, 5, 8]);
+    }
+
+    #[test]
+    fn take_every_with_offset_beyond_end_is_empty() {
+        assert_eq!((0..6).take_every(2, 6).next(), None);
+    }
+
+    #[test]
+    fn take_every_with_step_one_yields_everything_from_offset() {
+        assert_eq!((0..6).take_every(1, 3).collect::<Vec<_>>(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "step must be greater than zero")]
+    fn take_every_with_step_zero_panics() {
+        (0..6).take_every(0, 0);
+    }
+
+    #[test]
+    fn zip3_yields_flat_triples() {
+        let result = (1..4).zip3("abc".chars(), vec![true, false, true]).collect::<Vec<_>>();
+        assert_eq!(result, vec![(1, 'a', true), (2, 'b', false), (3, 'c', true)]);
+    }
+
+    #[test]
+    fn zip3_stops_at_shortest_sequence() {
+        assert_eq!((1..10).zip3(1..3, 1..5).count(), 2);
+        assert_eq!((1..10).zip3(1..5, empty::<i32>()).count(), 0);
+    }
+
+    #[test]
+    fn zip3_tuple_is_flat() {
+        let (a, b, c): (i32, char, &str) = once(1).zip3(once('x'), once("y")).next().unwrap();
+        assert_eq!((a, b, c), (1, 'x', "y"));
+    }
+
+    #[test]
+    fn index_of_finds_first_matching_value() {
+        assert_eq!(["a", "b", "c"].iter().index_of(&&"b"), Some(1));
+        assert_eq!((1..4).index_of(&9), None);
+    }
+
+    #[test]
+    fn index_of_with_repeated_value_returns_first_index() {
+        let mut it = [5, 7, 5, 7].iter();
+        assert_eq!(it.index_of(&&7), Some(1));
+        assert_eq!(it.next(), Some(&5));
+    }
+
+    #[test]
+    fn index_of_where_finds_first_element_matching_predicate() {
+        assert_eq!((10..20).index_of_where(|&x| x % 7 == 0), Some(4));
+        assert_eq!((10..20).index_of_where(|&x| x > 100), None);
+
+        let words = vec![String::from("red"), String::from("green")];
+        assert_eq!(words.into_iter().index_of_where(|w| w.starts_with('g')), Some(1));
+    }
+
+    #[test]
+    fn order_by_comparer_sorts_strings_case_insensitively() {
+        let words = vec!["b", "A", "a", "C"];
+        let natural = words.clone().into_iter().order_by(|w| *w).collect::<Vec<_>>();
+        assert_eq!(natural, vec!["A", "C", "a", "b"]);
+        let actual = words.into_iter().order_by_comparer(case_insensitive()).collect::<Vec<_>>();
+        assert_eq!(actual, vec!["A", "a", "b", "C"]);
+    }
+
+    #[test]
+    fn order_by_comparer_accepts_a_closure() {
+        let actual = (1..7)
+            .order_by_comparer(|a: &i32, b: &i32| (a % 3).cmp(&(b % 3)))
+            .then_by_descending(|x| *x)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![6, 3, 4, 1, 5, 2]);
+    }
+
+    #[test]
+    fn max_by_comparer_can_disagree_with_natural_ordering() {
+        let words = ["apple", "Zebra", "mango"];
+        assert_eq!(words.iter().max(), Some(&"mango"));
+        assert_eq!(words.iter().cloned().max_by_comparer(case_insensitive()), Some("Zebra"));
+        assert_eq!(words.iter().cloned().min_by_comparer(case_insensitive()), Some("apple"));
+    }
+
+    #[test]
+    fn min_and_max_by_comparer_return_first_element_on_tie() {
+        let words = ["b", "B", "a", "A"];
+        assert_eq!(words.iter().cloned().max_by_comparer(case_insensitive()), Some("b"));
+        assert_eq!(words.iter().cloned().min_by_comparer(case_insensitive()), Some("a"));
+        assert_eq!(empty::<&str>().max_by_comparer(case_insensitive()), None);
+    }
+
+    #[test]
+    fn distinct_with_dedupes_case_insensitively() {
+        let actual = ["A", "a", "b", "B", "c"].iter().cloned()
+            .distinct_with(case_insensitive())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["A", "b", "c"]);
+    }
+
+    #[test]
+    fn intersect_with_uses_custom_comparer() {
+        let actual = ["Apple", "pear", "PLUM", "apple"].iter().cloned()
+            .intersect_with(vec!["plum", "APPLE"], case_insensitive())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["Apple", "PLUM"]);
+    }
+
+    #[test]
+    fn except_with_and_union_with_use_custom_comparer() {
+        let left = vec!["Apple".to_string(), "pear".to_string(), "PEAR".to_string()];
+        let right = vec!["APPLE".to_string(), "plum".to_string()];
+
+        let except = left.clone().into_iter().except_with(right.clone(), case_insensitive()).collect::<Vec<_>>();
+        assert_eq!(except, vec!["pear"]);
+
+        let union = left.into_iter().union_with(right, case_insensitive()).collect::<Vec<_>>();
+        assert_eq!(union, vec!["Apple", "pear", "plum"]);
+    }
+
+    #[test]
+    fn to_hash_set_removes_duplicates() {
+        let set = [3, 1, 3, 2, 1].iter().to_hash_set();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&2));
+    }
+
+    #[test]
+    fn to_ordered_set_keeps_first_seen_order() {
+        let set = ["c", "a", "c", "b", "a"].iter().cloned().to_ordered_set();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&"b"));
+        assert!(!set.contains(&"d"));
+        assert!(!set.is_empty());
+        assert_eq!(set.as_slice(), &["c", "a", "b"]);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&"c", &"a", &"b"]);
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn to_ordered_set_of_empty_sequence_is_empty() {
+        let set = empty::<i32>().to_ordered_set();
+        assert!(set.is_empty());
+        assert_eq!(set.iter().next(), None);
+    }
+
+    #[test]
+    fn to_snapshot_can_be_inspected_and_iterated() {
+        let mut snapshot = (1..4).to_snapshot();
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot.as_slice(), &[1, 2, 3]);
+        assert_eq!(snapshot.clone().max(), Some(3));
+
+        assert_eq!(snapshot.next(), Some(1));
+        assert_eq!(snapshot.as_slice(), &[2, 3]);
+        assert_eq!(snapshot.collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn to_snapshot_from_filter_chain() {
+        let snapshot = (1..10).filter(|x| x % 3 == 0).to_snapshot();
+        assert!(!snapshot.is_empty());
+        assert_eq!(snapshot.as_slice(), &[3, 6, 9]);
+        assert_eq!(snapshot.sum_linq(), 18);
+    }
+
+    #[test]
+    fn deltas_of_increasing_sequence() {
+        assert_eq!([1, 4, 9, 16].iter().cloned().deltas().collect::<Vec<_>>(), vec![3, 5, 7]);
+        assert_eq!([1.5, 1.0].iter().cloned().deltas().collect::<Vec<_>>(), vec![-0.5]);
+    }
+
+    #[test]
+    fn deltas_of_constant_sequence_are_zero() {
+        assert_eq!([7, 7, 7].iter().cloned().deltas().collect::<Vec<_>>(), vec![0, 0]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overflow")]
+    fn deltas_of_decreasing_unsigned_sequence_panics_in_debug_builds() {
+        [5u32, 3].iter().cloned().deltas().for_each(drop);
+    }
+
+    #[test]
+    fn deltas_of_decreasing_sequence_converted_to_signed() {
+        let actual = [5u32, 3].iter().map(|&x| i64::from(x)).deltas().collect::<Vec<_>>();
+        assert_eq!(actual, vec![-2]);
+    }
+
+    #[test]
+    fn deltas_of_short_sequences_are_empty() {
+        assert_eq!(once(1).deltas().next(), None);
+        assert_eq!(empty::<i32>().deltas().next(), None);
+    }
+
+    #[test]
+    fn weighted_pick_with_seeded_rng_is_reproducible() {
+        let first = (1..10).weighted_pick(|&x| x as f64, &mut StdRng::seed_from_u64(3));
+        let second = (1..10).weighted_pick(|&x| x as f64, &mut StdRng::seed_from_u64(3));
+        assert!(first.is_some());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn weighted_pick_never_picks_zero_weight() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let picked = ["never", "sometimes", "often"].iter()
+                .weighted_pick(|w| match **w { "never" => 0.0, "sometimes" => 1.0, _ => 3.0 }, &mut rng);
+            assert_ne!(picked, Some(&"never"));
+        }
+    }
+
+    #[test]
+    fn weighted_pick_with_no_weight_is_none() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(empty::<i32>().weighted_pick(|_| 1.0, &mut rng), None);
+        assert_eq!((1..4).weighted_pick(|_| 0.0, &mut rng), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "weights must be finite and not negative")]
+    fn weighted_pick_with_negative_weight_panics() {
+        (1..4).weighted_pick(|_| -1.0, &mut StdRng::seed_from_u64(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "weights must be finite and not negative")]
+    fn weighted_pick_with_nan_weight_panics() {
+        (1..4).weighted_pick(|_| f64::NAN, &mut StdRng::seed_from_u64(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "weights must be finite and not negative")]
+    fn weighted_pick_with_infinite_weight_panics() {
+        (1..4).weighted_pick(|&x| if x == 2 { f64::INFINITY } else { 1.0 }, &mut StdRng::seed_from_u64(1));
+    }
+
+    #[test]
+    fn max_n_and_min_n_return_top_three() {
+        let values = [5, 1, 9, 3, 7, 2, 8];
+        assert_eq!(values.iter().max_n(3), vec![&9, &8, &7]);
+        assert_eq!(values.iter().min_n(3), vec![&1, &2, &3]);
+    }
+
+    #[test]
+    fn max_n_and_min_n_with_n_exceeding_length_return_all_sorted() {
+        assert_eq!([2, 3, 1].iter().max_n(10), vec![&3, &2, &1]);
+        assert_eq!([2, 3, 1].iter().min_n(10), vec![&1, &2, &3]);
+    }
+
+    #[test]
+    fn max_n_and_min_n_with_zero_n_are_empty() {
+        assert_eq!((1..5).max_n(0), Vec::<i32>::new());
+        assert_eq!((1..5).min_n(0), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn max_n_and_min_n_by_key_keep_original_order_for_ties() {
+        let people = [person("Fred", "Smith", 40), person("Alice", "Jones", 30),
+                      person("Bob", "Smith", 40), person("Jane", "Doe", 30), person("Ann", "Doe", 20)];
+        let oldest = people.iter().max_n_by_key(3, |p| p.age).into_iter().map(|p| p.first).collect::<Vec<_>>();
+        assert_eq!(oldest, vec!["Fred", "Bob", "Alice"]);
+        let youngest = people.iter().min_n_by_key(2, |p| p.age).into_iter().map(|p| p.first).collect::<Vec<_>>();
+        assert_eq!(youngest, vec!["Ann", "Alice"]);
+    }
+
+    #[test]
+    fn at_least_checks_boundary_counts() {
+        assert!((1..4).at_least(0));
+        assert!((1..4).at_least(3));
+        assert!(!(1..4).at_least(4));
+    }
 
-// Step 2: Use that trait to add extension methods to the Iterator trait.
-// The impl block itself is empty because we defaulted all the method definitions
-// in the trait definition itself.
-impl<T> LinqIteratorExtensions for T where T: Iterator { }
+    #[test]
+    fn at_most_checks_boundary_counts() {
+        assert!((1..4).at_most(3));
+        assert!(!(1..4).at_most(2));
+        assert!(empty::<i32>().at_most(0));
+    }
 
+    #[test]
+    fn exactly_checks_boundary_counts() {
+        assert!((1..4).exactly(3));
+        assert!(!(1..4).exactly(2));
+        assert!(!(1..4).exactly(4));
+        assert!(empty::<i32>().exactly(0));
+    }
 
-// Step 3: Define the structs required by our adapters, if any: these are only required if the
-// adapters need to manage state. n.b. Step 3 implies you need step 4 as well.
-pub struct Intersect<A, B>
-    where A: Iterator,
-          B: Iterator
-{
-    a: A,
-    b: B,
-    items: HashSet<A::Item>
-}
+    #[test]
+    fn cardinality_checks_do_not_overflow_at_usize_max() {
+        assert!((1..4).at_most(usize::MAX));
+        assert!(empty::<i32>().at_most(usize::MAX));
+        assert!(!(1..4).exactly(usize::MAX));
+        assert!(!empty::<i32>().exactly(usize::MAX));
+        assert!(!(1..4).at_least(usize::MAX));
+    }
 
-// Step 4: Implement Iterator functionality for our structs.
-impl<A, B> Iterator for Intersect<A, B>
-    where A: Iterator,
-          B: Iterator<Item = A::Item>,
-          A::Item: Eq + hash::Hash
-{
-    type Item = A::Item;
+    #[test]
+    fn cardinality_checks_short_circuit_on_infinite_sequences() {
+        let mut naturals = 0..;
+        assert!(naturals.at_least(5));
+        assert_eq!(naturals.next(), Some(5));
 
-    #[inline]
-    fn next(&mut self) -> Option<A::Item> {
-        //self.items = self.b.collect();
-        //self.b
-        None
+        let mut naturals = (0..).take(usize::MAX);
+        assert!(!naturals.at_most(2));
+        assert_eq!(naturals.next(), Some(3));
+        assert!(!naturals.exactly(10));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::LinqIteratorExtensions;
-    use std::iter::{empty, once};
+    #[test]
+    fn group_by_aggregate_sums_per_parity() {
+        let sums = (1..8).group_by_aggregate(|x| x % 2 == 0, || 0, |total, x| total + x);
+        assert_eq!(sums.len(), 2);
+        assert_eq!(sums[&true], 2 + 4 + 6);
+        assert_eq!(sums[&false], 1 + 3 + 5 + 7);
+    }
 
     #[test]
-    fn single_for_empty_sequence_returns_none() {
-        let actual = empty::<i32>().single();
-        assert_eq!(actual, None);
+    fn group_by_aggregate_concatenates_per_first_letter() {
+        let words = ["apple", "banana", "avocado", "blueberry", "cherry"];
+        let seeds = std::cell::Cell::new(0);
+        let joined = words.iter().group_by_aggregate(
+            |w| w.chars().next().unwrap(),
+            || { seeds.set(seeds.get() + 1); String::new() },
+            |mut s, w| { s.push_str(w); s });
+
+        assert_eq!(joined[&'a'], "appleavocado");
+        assert_eq!(joined[&'b'], "bananablueberry");
+        assert_eq!(joined[&'c'], "cherry");
+        // One accumulator per group, rather than a list of elements.
+        assert_eq!(seeds.get(), 3);
     }
 
     #[test]
-    fn single_for_singleton_sequence_returns_none() {
-        let actual = once(12).single();
-        assert_eq!(actual, Some(12));
+    fn enumerate_from_one() {
+        let result = ["a", "b"].iter().enumerate_from(1).collect::<Vec<_>>();
+        assert_eq!(result, vec![(1, &"a"), (2, &"b")]);
     }
 
     #[test]
-    fn single_for_sequence_of_length_two_or_more_returns_none() {
-        let actual = (0..2).single();
-        assert_eq!(actual, None);
+    fn enumerate_from_large_offset() {
+        let result = (0..3).enumerate_from(1000).map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(result, vec![1000, 1001, 1002]);
+    }
 
-        let actual = (0..3).single();
-        assert_eq!(actual, None);
+    #[test]
+    fn enumerate_from_over_empty_sequence_yields_nothing() {
+        assert_eq!(empty::<i32>().enumerate_from(5).next(), None);
     }
 
     #[test]
-    fn single_or_for_empty_sequence_returns_default() {
-        let actual = empty::<i32>().single_or(42);
-        assert_eq!(actual, 42);
+    fn chunk_by_splits_into_ascending_runs() {
+        let result = [1, 2, 3, 1, 2].iter().chunk_by(|a, b| b < a).collect::<Vec<_>>();
+        assert_eq!(result, vec![vec![&1, &2, &3], vec![&1, &2]]);
     }
 
     #[test]
-    fn single_or_for_singleton_sequence_returns_value_from_sequence() {
-        let actual = once(12).single_or(42);
-        assert_eq!(actual, 12);
+    fn chunk_by_that_never_splits_gives_one_chunk() {
+        let result = (1..5).chunk_by(|_, _| false).collect::<Vec<_>>();
+        assert_eq!(result, vec![vec![1, 2, 3, 4]]);
+        assert_eq!(empty::<i32>().chunk_by(|_, _| false).next(), None);
     }
 
     #[test]
-    fn single_or_for_sequence_of_length_two_or_more_returns_default() {
-        let actual = (0..2).single_or(42);
-        assert_eq!(actual, 42);
+    fn dedup_with_count_collapses_adjacent_runs() {
+        let result = ["a", "a", "b", "a"].iter().dedup_with_count().collect::<Vec<_>>();
+        assert_eq!(result, vec![(&"a", 2), (&"b", 1), (&"a", 1)]);
+    }
 
-        let actual = (0..3).single_or(42);
-        assert_eq!(actual, 42);
+    #[test]
+    fn dedup_with_count_by_collapses_by_key() {
+        let result = users().into_iter()
+            .order_by(|u| u.id)
+            .dedup_with_count_by(|u| u.id)
+            .map(|(u, count)| (u.name, count))
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec![("alice", 1), ("bob", 2), ("carol", 1)]);
     }
 
-    fn default_generator() -> i32 {
-        130
+    #[test]
+    fn product_linq_multiplies_elements() {
+        assert_eq!([1, 2, 3, 4].iter().cloned().product_linq(), 24);
+        assert_eq!(empty::<i32>().product_linq(), 1);
     }
 
     #[test]
-    fn single_or_else_for_empty_sequence_returns_default() {
-        let actual = empty::<i32>().single_or_else(default_generator);
-        assert_eq!(actual, 130);
+    fn product_by_multiplies_projected_values() {
+        let odds = [("heads", 0.5), ("six", 1.0 / 6.0)];
+        let combined: f64 = odds.iter().product_by(|o| o.1);
+        assert!((combined - 1.0 / 12.0).abs() < 1e-12);
+        assert_eq!(empty::<&str>().product_by(|w| w.len()), 1);
     }
 
     #[test]
-    fn single_or_else_for_singleton_sequence_returns_value_from_sequence() {
-        let actual = once(12).single_or_else(default_generator);
-        assert_eq!(actual, 12);
+    fn cumulative_sum_yields_running_totals() {
+        assert_eq!((1..4).cumulative_sum().collect::<Vec<_>>(), vec![1, 3, 6]);
+        assert_eq!([5, -2, -4, 1].iter().cloned().cumulative_sum().collect::<Vec<_>>(), vec![5, 3, -1, 0]);
     }
 
     #[test]
-    fn single_or_else_for_sequence_of_length_two_or_more_returns_default() {
-        let actual = (0..2).single_or_else(default_generator);
-        assert_eq!(actual, 130);
+    fn cumulative_max_yields_running_maximum() {
+        assert_eq!([1, 3, 2, 5].iter().cumulative_max().collect::<Vec<_>>(), vec![&1, &3, &3, &5]);
+        assert_eq!([9, 4, 2].iter().cumulative_max().collect::<Vec<_>>(), vec![&9, &9, &9]);
+    }
 
-        let actual = (0..3).single_or_else(default_generator);
-        assert_eq!(actual, 130);
+    #[test]
+    fn skip_until_starts_at_first_match() {
+        assert_eq!((1..6).skip_until(|&x| x > 3).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!([5, 1, 5].iter().skip_until(|&&x| x < 3).collect::<Vec<_>>(), vec![&1, &5]);
+        assert_eq!((1..6).skip_until(|&x| x > 10).next(), None);
     }
 
     #[test]
-    fn single_or_default_for_empty_sequence_returns_default() {
-        let actual = empty::<i32>().single_or_default();
-        assert_eq!(actual, 0);
+    fn take_until_includes_triggering_element() {
+        assert_eq!((1..6).take_until(|&x| x > 3).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!((1..6).take_until(|&x| x > 10).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
     }
 
     #[test]
-    fn single_or_default_for_singleton_sequence_returns_value_from_sequence() {
-        let actual = once(12).single_or_default();
-        assert_eq!(actual, 12);
+    fn order_by_keys_sorts_by_dynamically_chosen_keys() {
+        let columns = ["last", "first"];
+        let keys = columns.iter()
+            .map(|&c| -> Box<dyn Fn(&Person) -> &'static str> {
+                match c {
+                    "first" => Box::new(|p| p.first),
+                    _ => Box::new(|p| p.last)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let people = vec![person("Fred", "Smith", 40), person("Alice", "Jones", 30), person("Bob", "Smith", 20)];
+        let actual = people.into_iter().order_by_keys(keys).map(|p| p.first).collect::<Vec<_>>();
+        assert_eq!(actual, vec!["Alice", "Bob", "Fred"]);
     }
 
     #[test]
-    fn single_or_default_for_sequence_of_length_two_or_more_returns_default() {
-        let actual = (0..2).single_or_default();
-        assert_eq!(actual, 0);
+    fn order_by_keys_with_single_key_is_order_by() {
+        let keys: Vec<fn(&Person) -> u32> = vec![|p| p.age];
+        let people = vec![person("Fred", "Smith", 40), person("Alice", "Jones", 30), person("Bob", "Smith", 20)];
+        let actual = people.into_iter().order_by_keys(keys).map(|p| p.age).collect::<Vec<_>>();
+        assert_eq!(actual, vec![20, 30, 40]);
+    }
 
-        let actual = (0..3).single_or_default();
-        assert_eq!(actual, 0);
+    #[test]
+    fn order_by_keys_with_no_keys_preserves_order() {
+        let keys: Vec<fn(&i32) -> i32> = vec![];
+        assert_eq!([3, 1, 2].iter().cloned().order_by_keys(keys).collect::<Vec<_>>(), vec![3, 1, 2]);
     }
 
     #[test]
-    fn first_or_for_empty_sequence_returns_default() {
-        assert_eq!(empty::<i32>().first_or(42), 42);
+    fn rank_skips_after_ties() {
+        let ranked = [30, 10, 20, 20].iter().cloned().rank();
+        assert_eq!(ranked, vec![(1, 10), (2, 20), (2, 20), (4, 30)]);
     }
 
     #[test]
-    fn first_or_for_singleton_sequence_returns_first_value_from_sequence() {
-        assert_eq!(once(12).first_or(42), 12);
+    fn dense_rank_does_not_skip_after_ties() {
+        let ranked = [30, 10, 20, 20].iter().cloned().dense_rank();
+        assert_eq!(ranked, vec![(1, 10), (2, 20), (2, 20), (3, 30)]);
     }
 
     #[test]
-    fn first_or_for_sequence_of_length_two_or_more_returns_first_value_from_sequence() {
-        let actual = (1..3).first_or(42);
-        assert_eq!(actual, 1);
+    fn rank_and_dense_rank_of_empty_sequence_are_empty() {
+        assert_eq!(empty::<i32>().rank(), vec![]);
+        assert_eq!(empty::<i32>().dense_rank(), vec![]);
+    }
 
-        let actual = (1..4).first_or(42);
-        assert_eq!(actual, 1);
+    fn add_if_positive(total: i32, x: i32) -> Result<i32, i32> {
+        if x > 0 { Ok(total + x) } else { Err(x) }
     }
 
     #[test]
-    fn first_or_else_for_empty_sequence_returns_default() {
-        assert_eq!(empty::<i32>().first_or_else(default_generator), 130);
+    fn try_aggregate_stops_at_first_error() {
+        let mut seen = vec![];
+        let result = [1, 2, -3, 4, -5].iter().cloned()
+            .inspect(|&x| seen.push(x))
+            .try_aggregate(0, add_if_positive);
+        assert_eq!(result, Err(-3));
+        assert_eq!(seen, vec![1, 2, -3]);
     }
 
     #[test]
-    fn first_or_else_for_singleton_sequence_returns_first_value_from_sequence() {
-        assert_eq!(once(12).first_or_else(default_generator), 12);
+    fn try_aggregate_returns_ok_when_every_step_succeeds() {
+        assert_eq!((1..5).try_aggregate(0, add_if_positive), Ok(10));
+        assert_eq!(empty::<i32>().try_aggregate(7, add_if_positive), Ok(7));
     }
 
     #[test]
-    fn first_or_else_for_sequence_of_length_two_or_more_returns_first_value_from_sequence() {
-        let actual = (1..3).first_or_else(default_generator);
-        assert_eq!(actual, 1);
+    fn split_at_linq_splits_at_midpoint_leaving_suffix_unread() {
+        let mut reads = 0;
+        {
+            let (prefix, mut suffix) = (1..100).inspect(|_| reads += 1).split_at_linq(2);
+            assert_eq!(prefix, vec![1, 2]);
+            assert_eq!(suffix.next(), Some(3));
+        }
+        assert_eq!(reads, 3);
+    }
 
-        let actual = (1..4).first_or_else(default_generator);
-        assert_eq!(actual, 1);
+    #[test]
+    fn split_at_linq_at_zero_gives_empty_prefix() {
+        let (prefix, suffix) = (1..4).split_at_linq(0);
+        assert_eq!(prefix, Vec::<i32>::new());
+        assert_eq!(suffix.collect::<Vec<_>>(), vec![1, 2, 3]);
     }
 
     #[test]
-    fn first_or_default_for_empty_sequence_returns_default() {
-        assert_eq!(empty::<i32>().first_or_default(), 0);
+    fn split_at_linq_past_end_gives_everything_in_prefix() {
+        let (prefix, mut suffix) = (1..4).split_at_linq(10);
+        assert_eq!(prefix, vec![1, 2, 3]);
+        assert_eq!(suffix.next(), None);
     }
 
     #[test]
-    fn first_or_default_for_singleton_sequence_returns_first_value_from_sequence() {
-        assert_eq!(once(12).first_or_default(), 12);
+    fn rolling_computes_moving_average() {
+        let actual = [1.0, 2.0, 6.0, 4.0, 8.0].iter().cloned()
+            .rolling(3, |w| w.iter().sum::<f64>() / w.len() as f64)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![3.0, 4.0, 6.0]);
     }
 
     #[test]
-    fn first_or_default_for_sequence_of_length_two_or_more_returns_first_value_from_sequence() {
-        let actual = (1..3).first_or_default();
-        assert_eq!(actual, 1);
+    fn rolling_with_window_larger_than_sequence_is_empty() {
+        assert_eq!((1..3).rolling(3, |w| w.len()).next(), None);
+    }
 
-        let actual = (1..4).first_or_default();
-        assert_eq!(actual, 1);
+    #[test]
+    fn rolling_with_window_of_one_reduces_each_element() {
+        let actual = (1..4).rolling(1, |w| w[0] * 10).collect::<Vec<_>>();
+        assert_eq!(actual, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn aggregate_right_appends_from_the_right() {
+        let actual = ["a", "b", "c"].iter().aggregate_right(String::new(), |x, mut acc| {
+            acc.push_str(x);
+            acc
+        });
+        assert_eq!(actual, "cba");
+    }
+
+    #[test]
+    fn aggregate_right_is_right_associative() {
+        // 1 - (2 - (3 - 0)) = 2, whereas folding from the left gives ((0 - 1) - 2) - 3 = -6.
+        assert_eq!((1..4).aggregate_right(0, |x, acc| x - acc), 2);
+        assert_eq!((1..4).aggregate_with(0, |acc, x| acc - x), -6);
+    }
+
+    #[test]
+    fn ngrams_yields_bigrams_and_trigrams() {
+        assert_eq!("hello".chars().ngrams(2).collect::<Vec<_>>(), vec!["he", "el", "ll", "lo"]);
+        assert_eq!("hello".chars().ngrams(3).collect::<Vec<_>>(), vec!["hel", "ell", "llo"]);
+    }
+
+    #[test]
+    fn ngrams_with_n_larger_than_input_is_empty() {
+        assert_eq!("hi".chars().ngrams(3).next(), None);
+    }
+
+    #[test]
+    fn ngrams_with_n_of_one_yields_each_char() {
+        assert_eq!("abc".chars().ngrams(1).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn first_duplicate_finds_repeat_in_the_middle() {
+        let mut it = [1, 2, 3, 2, 1, 4].iter();
+        assert_eq!(it.first_duplicate(), Some(&2));
+        assert_eq!(it.next(), Some(&1));
+    }
+
+    #[test]
+    fn first_duplicate_of_distinct_sequence_is_none() {
+        assert_eq!((1..10).first_duplicate(), None);
+    }
+
+    #[test]
+    fn first_duplicate_when_first_element_repeats_immediately() {
+        let mut it = "aab".chars();
+        assert_eq!(it.first_duplicate(), Some('a'));
+        assert_eq!(it.next(), Some('b'));
+    }
+
+    #[test]
+    fn transpose_rows_of_rectangular_matrix() {
+        let rows = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(rows.into_iter().transpose_rows(), vec![vec![1, 4], vec![2, 5], vec![3, 6]]);
+    }
+
+    #[test]
+    fn transpose_rows_of_single_row() {
+        let rows = vec![vec!["a", "b"]];
+        assert_eq!(rows.into_iter().transpose_rows(), vec![vec!["a"], vec!["b"]]);
+    }
+
+    #[test]
+    fn transpose_rows_truncates_ragged_rows_to_shortest() {
+        let rows = vec![vec![1, 2, 3], vec![4], vec![7, 8]];
+        assert_eq!(rows.into_iter().transpose_rows(), vec![vec![1, 4, 7]]);
+        assert_eq!(empty::<Vec<i32>>().transpose_rows(), Vec::<Vec<i32>>::new());
+    }
+
+    #[test]
+    fn split_on_splits_at_delimiters() {
+        let actual = [1, 0, 2, 3, 0, 4].iter().cloned().split_on(|&x| x == 0).collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![1], vec![2, 3], vec![4]]);
+    }
+
+    #[test]
+    fn split_on_gives_empty_segments_for_leading_and_trailing_delimiters() {
+        let actual = "_ab__c_".chars().split_on(|&c| c == '_').collect::<Vec<_>>();
+        assert_eq!(actual, vec![vec![], vec!['a', 'b'], vec![], vec!['c'], vec![]]);
+    }
+
+    #[test]
+    fn split_on_without_delimiters_gives_one_segment() {
+        assert_eq!((1..4).split_on(|&x| x == 0).collect::<Vec<_>>(), vec![vec![1, 2, 3]]);
+        assert_eq!(empty::<i32>().split_on(|&x| x == 0).collect::<Vec<_>>(), vec![Vec::<i32>::new()]);
+    }
+
+    #[test]
+    fn to_list_collects_filtered_sequence() {
+        assert_eq!((1..10).filter(|x| x % 3 == 0).to_list(), vec![3, 6, 9]);
+    }
+
+    #[test]
+    fn to_list_uses_size_hint() {
+        let list = (0..100).map(|x| x * 2).to_list();
+        assert_eq!(list.len(), 100);
+        assert_eq!(list.capacity(), 100);
+    }
+
+    #[test]
+    fn to_array_collects_filtered_sequence_into_boxed_slice() {
+        let array: Box<[i32]> = (1..10).filter(|x| x % 3 == 0).to_array();
+        assert_eq!(&*array, &[3, 6, 9]);
+    }
+
+    #[test]
+    fn merge_sorted_merges_two_sorted_sequences() {
+        let actual = [1, 3, 5].iter().merge_sorted([2, 4, 6].iter()).collect::<Vec<_>>();
+        assert_eq!(actual, vec![&1, &2, &3, &4, &5, &6]);
+    }
+
+    #[test]
+    fn merge_sorted_with_an_empty_side() {
+        assert_eq!((1..4).merge_sorted(empty()).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(empty().merge_sorted(1..4).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn merge_sorted_by_is_stable_on_ties() {
+        let left = vec![(1, "left"), (2, "left")];
+        let right = vec![(1, "right"), (2, "right"), (3, "right")];
+        let actual = left.into_iter()
+            .merge_sorted_by(right, |a, b| a.0.cmp(&b.0))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![(1, "left"), (1, "right"), (2, "left"), (2, "right"), (3, "right")]);
+    }
+
+    #[test]
+    fn repeat_sequence_repeats_whole_sequence() {
+        let actual = [1, 2].iter().repeat_sequence(3).collect::<Vec<_>>();
+        assert_eq!(actual, vec![&1, &2, &1, &2, &1, &2]);
+    }
+
+    #[test]
+    fn repeat_sequence_zero_times_is_empty() {
+        assert_eq!((1..3).repeat_sequence(0).next(), None);
+    }
+
+    #[test]
+    fn repeat_sequence_once_is_unchanged() {
+        assert_eq!((1..4).repeat_sequence(1).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn repeat_sequence_of_empty_source_is_empty() {
+        assert_eq!(empty::<i32>().repeat_sequence(5).next(), None);
+    }
+
+    #[test]
+    fn coalesce_replaces_nones_with_fallback() {
+        let actual = vec![Some(1), None, Some(3)].into_iter().coalesce(0).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 0, 3]);
+    }
+
+    #[test]
+    fn coalesce_of_all_somes_is_unchanged() {
+        let actual = vec![Some("a"), Some("b")].into_iter().coalesce("-").collect::<Vec<_>>();
+        assert_eq!(actual, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn coalesce_of_all_nones_is_all_fallbacks() {
+        let actual = vec![None, None].into_iter().coalesce(7).collect::<Vec<_>>();
+        assert_eq!(actual, vec![7, 7]);
+    }
+
+    #[test]
+    fn every_nth_reduce_reduces_full_and_partial_groups() {
+        let actual = (1..8).every_nth_reduce(3, |g| g.iter().sum::<i32>()).collect::<Vec<_>>();
+        assert_eq!(actual, vec![6, 15, 7]);
+    }
+
+    #[test]
+    fn every_nth_reduce_of_short_sequence_gives_one_partial_group() {
+        let actual = (1..3).every_nth_reduce(4, |g| g.len()).collect::<Vec<_>>();
+        assert_eq!(actual, vec![2]);
+    }
+
+    #[test]
+    fn every_nth_reduce_of_empty_sequence_is_empty() {
+        assert_eq!(empty::<i32>().every_nth_reduce(3, |g| g.len()).next(), None);
+    }
+
+    #[test]
+    fn pad_right_and_pad_left_pad_short_sequence() {
+        assert_eq!((1..4).pad_right(5, 0).collect::<Vec<_>>(), vec![1, 2, 3, 0, 0]);
+        assert_eq!((1..4).pad_left(5, 0).collect::<Vec<_>>(), vec![0, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn pad_right_and_pad_left_leave_long_sequence_unchanged() {
+        assert_eq!((1..7).pad_right(5, 0).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!((1..7).pad_left(5, 0).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn pad_right_and_pad_left_fill_empty_sequence() {
+        assert_eq!(empty().pad_right(3, 'x').collect::<String>(), "xxx");
+        assert_eq!(empty().pad_left(3, 'x').collect::<String>(), "xxx");
+    }
+
+    fn running_total_if_positive(total: &i32, x: i32) -> Result<i32, String> {
+        if x > 0 { Ok(total + x) } else { Err(format!("bad value {}", x)) }
+    }
+
+    #[test]
+    fn scan_try_yields_each_accumulator_when_all_succeed() {
+        let actual = (1..4).scan_try(0, running_total_if_positive).collect::<Vec<_>>();
+        assert_eq!(actual, vec![Ok(1), Ok(3), Ok(6)]);
+    }
+
+    #[test]
+    fn scan_try_stops_after_first_error() {
+        let actual = [1, 2, -3, 4].iter().cloned().scan_try(0, running_total_if_positive).collect::<Vec<_>>();
+        assert_eq!(actual, vec![Ok(1), Ok(3), Err("bad value -3".to_string())]);
+    }
+
+    #[test]
+    fn scan_try_with_immediate_failure() {
+        let actual = [0, 1].iter().cloned().scan_try(0, running_total_if_positive).collect::<Vec<_>>();
+        assert_eq!(actual, vec![Err("bad value 0".to_string())]);
     }
 
+    #[test]
+    fn count_distinct_counts_unique_values() {
+        assert_eq!([1, 1, 2, 3, 3].iter().count_distinct(), 3);
+    }
+
+    #[test]
+    fn count_distinct_by_counts_unique_keys() {
+        assert_eq!(users().into_iter().count_distinct_by(|u| u.id), 3);
+    }
+
+    #[test]
+    fn count_distinct_of_empty_sequence_is_zero() {
+        assert_eq!(empty::<i32>().count_distinct(), 0);
+        assert_eq!(empty::<i32>().count_distinct_by(|x| *x), 0);
+    }
 
-//    #[test]
-//    fn in_range_works() {
-//        let v= vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-//        let result = v.into_iter().in_range(0..3).collect::<Vec<_>>();
-//        assert_eq!(result, vec![0, 1, 2]);
-//
-//        let v= vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-//        let result = v.into_iter().in_range(4..8).collect::<Vec<_>>();
-//        assert_eq!(result, vec![4, 5, 6, 7]);
-//
-//        // TODO: This is ugly...
-//        let v= vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-//        let result = v.iter().in_range(&4..&8).collect::<Vec<_>>();
-//        assert_eq!(result, vec![&4, &5, &6, &7]);
-//    }
+    // std has unstable `Iterator::intersperse` and `Iterator::intersperse_with` methods, so
+    // to avoid the name clash we call ours through the trait.
+    #[test]
+    fn intersperse_puts_separator_between_elements() {
+        let actual = LinqIteratorExtensions::intersperse(1..4, 0).collect::<Vec<_>>();
+        assert_eq!(actual, vec![1, 0, 2, 0, 3]);
+    }
+
+    #[test]
+    fn intersperse_of_singleton_and_empty_sequence_adds_no_separator() {
+        assert_eq!(LinqIteratorExtensions::intersperse(once(1), 0).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(LinqIteratorExtensions::intersperse(empty(), 0).next(), None);
+    }
+
+    #[test]
+    fn intersperse_with_calls_separator_function_each_time() {
+        let mut n = 0;
+        let actual = LinqIteratorExtensions::intersperse_with((1..4).map(|x| x * 10), || { n += 1; n }).collect::<Vec<_>>();
+        assert_eq!(actual, vec![10, 1, 20, 2, 30]);
+        assert_eq!(LinqIteratorExtensions::intersperse_with(once(5), || 0).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(LinqIteratorExtensions::intersperse_with(empty(), || 0).next(), None);
+    }
 }
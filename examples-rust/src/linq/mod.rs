@@ -8,3 +8,4 @@ pub mod select_tests;
 pub mod single_tests;
 pub mod where_tests;
 pub mod linq_adapters;
+pub mod generators;
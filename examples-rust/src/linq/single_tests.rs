@@ -1,26 +1,41 @@
 mod tests {
     use std::iter::Iterator;
 
-    // struct Single<I> where I : Iterator {
-    //     iter: I,
-    //     item: Option<I::Item>
-    // }
-
-    // impl<I> Iterator for Single<I>
-    //     where I: Iterator
-    // {
-    //     type Item = I::Item;
-
-    //     fn next(&mut self) -> Option<Self::Item> {
-    //         match self.iter.next() {
-    //             None => None,
-    //             Some(x) => match self.iter.next() {
-    //                 None => Some(x),
-    //                 Some(_) => None
-    //             }
-    //         }
-    //     }
-    // }
+    // A lazy, struct-based version of `single`. The first call to `next` reads the source
+    // to check that it contains exactly one element and yields it; every call after that
+    // yields `None`.
+    //
+    // This works, but it is awkward compared to the `single` method below. Single is really a
+    // terminal operation - the answer is one value, not a sequence - so all you can usefully
+    // do with the adapter is call `next` on it once, which is just the method with extra
+    // steps. It also needs somewhere to remember that it has already run, because the
+    // source iterator is left partially consumed and asking it again would give the wrong
+    // answer.
+    struct Single<I> where I : Iterator {
+        iter: I,
+        done: bool
+    }
+
+    impl<I> Iterator for Single<I>
+        where I: Iterator
+    {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.done {
+                return None;
+            }
+
+            self.done = true;
+            match self.iter.next() {
+                None => None,
+                Some(x) => match self.iter.next() {
+                    None => Some(x),
+                    Some(_) => None
+                }
+            }
+        }
+    }
 
     trait SingleIteratorAdapter : Iterator {
         fn single(&mut self) -> Option<Self::Item>
@@ -33,6 +48,12 @@ mod tests {
                 }
             }
         }
+
+        fn single_lazy(self) -> Single<Self>
+            where Self: Sized
+        {
+            Single { iter: self, done: false }
+        }
     }
 
     impl<I> SingleIteratorAdapter for I where I : Iterator { }
@@ -76,4 +97,23 @@ mod tests {
         let result = source.iter().single();
         assert_eq!(None, result);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn single_ints_via_lazy_iterator_adapter() {
+        let source = [10];
+        let mut result = source.iter().single_lazy();
+        assert_eq!(Some(&10), result.next());
+        assert_eq!(None, result.next());
+
+        let source : Vec<i32> = vec![];
+        let mut result = source.iter().single_lazy();
+        assert_eq!(None, result.next());
+    }
+
+    #[test]
+    fn single_lazy_for_two_elements_yields_nothing() {
+        let source = [10, 20];
+        let result : Vec<&i32> = source.iter().single_lazy().collect();
+        assert!(result.is_empty());
+    }
+}
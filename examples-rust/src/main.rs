@@ -1,3 +1,9 @@
+extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
 mod linq;
 mod strings;
 mod common_traits;